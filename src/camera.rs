@@ -0,0 +1,84 @@
+/// The part of the map shown in the map console. `x` and `y` are the world
+/// coordinates of its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Camera {
+    pub fn new(width: i32, height: i32) -> Self {
+        Camera {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// Centres on `(target_x, target_y)`, stopping at the edges of the map so
+    /// nothing past them is shown. A map smaller than the view stays in the
+    /// top left corner.
+    pub fn follow(&mut self, target_x: i32, target_y: i32, map_width: i32, map_height: i32) {
+        self.x = clamp(target_x - self.width / 2, map_width - self.width);
+        self.y = clamp(target_y - self.height / 2, map_height - self.height);
+    }
+
+    /// Where a world position appears on screen, if it is in view at all.
+    pub fn to_screen(self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (screen_x, screen_y) = (x - self.x, y - self.y);
+        if screen_x >= 0 && screen_y >= 0 && screen_x < self.width && screen_y < self.height {
+            Some((screen_x, screen_y))
+        } else {
+            None
+        }
+    }
+
+    /// The world position under a screen cell, if the cell is inside the view.
+    pub fn to_world(self, screen_x: i32, screen_y: i32) -> Option<(i32, i32)> {
+        if screen_x >= 0 && screen_y >= 0 && screen_x < self.width && screen_y < self.height {
+            Some((screen_x + self.x, screen_y + self.y))
+        } else {
+            None
+        }
+    }
+}
+
+fn clamp(value: i32, max: i32) -> i32 {
+    value.min(max).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_stops_at_the_map_edges() {
+        let mut camera = Camera::new(80, 43);
+
+        camera.follow(100, 60, 200, 100);
+        assert_eq!((camera.x, camera.y), (60, 39));
+
+        camera.follow(3, 2, 200, 100);
+        assert_eq!((camera.x, camera.y), (0, 0));
+
+        camera.follow(198, 99, 200, 100);
+        assert_eq!((camera.x, camera.y), (120, 57));
+
+        camera.follow(30, 20, 60, 30);
+        assert_eq!((camera.x, camera.y), (0, 0));
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let mut camera = Camera::new(80, 43);
+        camera.follow(100, 60, 200, 100);
+
+        assert_eq!(camera.to_screen(100, 60), Some((40, 21)));
+        assert_eq!(camera.to_world(40, 21), Some((100, 60)));
+        assert_eq!(camera.to_screen(59, 60), None);
+        assert_eq!(camera.to_world(0, 43), None);
+    }
+}
@@ -0,0 +1,56 @@
+/// A plain RGB colour, for the game to describe how things look without
+/// caring which frontend draws them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+}
+
+pub const BLACK: Color = Color::new(0, 0, 0);
+pub const LIGHT_GREY: Color = Color::new(159, 159, 159);
+pub const WHITE: Color = Color::new(255, 255, 255);
+
+pub const RED: Color = Color::new(255, 0, 0);
+pub const ORANGE: Color = Color::new(255, 127, 0);
+pub const YELLOW: Color = Color::new(255, 255, 0);
+pub const GREEN: Color = Color::new(0, 255, 0);
+
+pub const LIGHT_RED: Color = Color::new(255, 63, 63);
+pub const LIGHT_ORANGE: Color = Color::new(255, 159, 63);
+pub const LIGHT_YELLOW: Color = Color::new(255, 255, 63);
+pub const LIGHT_GREEN: Color = Color::new(63, 255, 63);
+pub const LIGHT_BLUE: Color = Color::new(63, 63, 255);
+pub const LIGHT_VIOLET: Color = Color::new(159, 63, 255);
+pub const LIGHT_MAGENTA: Color = Color::new(255, 63, 207);
+
+pub const DARK_RED: Color = Color::new(191, 0, 0);
+pub const DARKER_RED: Color = Color::new(127, 0, 0);
+
+/// The colour `coefficient` of the way from `from` to `to`.
+pub fn lerp(from: Color, to: Color, coefficient: f32) -> Color {
+    let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * coefficient) as u8;
+    Color::new(
+        channel(from.r, to.r),
+        channel(from.g, to.g),
+        channel(from.b, to.b),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lerp_runs_from_one_colour_to_the_other() {
+        assert_eq!(lerp(BLACK, WHITE, 0.0), BLACK);
+        assert_eq!(lerp(BLACK, WHITE, 1.0), WHITE);
+        assert_eq!(lerp(WHITE, RED, 0.5), Color::new(255, 127, 127));
+    }
+}
@@ -1,7 +1,10 @@
-use tcod::colors::*;
-use tcod::console::*;
+use serde::Deserialize;
 
-use crate::game::{Game, Map};
+use crate::color::*;
+use crate::game::Game;
+use crate::light::Light;
+use crate::map::Map;
+use crate::trap::Trap;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Fighter {
@@ -36,17 +39,91 @@ pub enum AI {
         previous_ai: Box<AI>,
         num_turns: i32,
     },
+    /// Heads for the player whether it can see them or not.
+    Alerted {
+        previous_ai: Box<AI>,
+        num_turns: i32,
+    },
 }
 
+/// How a monster keeps track of the player, apart from whatever the player
+/// can see.
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Senses {
+    /// How far it can see. A blind monster, with 0, only notices the player
+    /// right next to it.
+    pub sight_radius: i32,
+    /// How many turns it keeps after the player once it loses them.
+    pub memory: i32,
+    /// Where it last noticed the player, until it forgets.
+    pub last_seen: Option<(i32, i32)>,
+    pub turns_left: i32,
+}
+
+impl Senses {
+    pub fn new(sight_radius: i32, memory: i32) -> Self {
+        Senses {
+            sight_radius,
+            memory,
+            last_seen: None,
+            turns_left: 0,
+        }
+    }
+
+    /// Updates what the monster knows after a turn in which it did, or did
+    /// not, notice the player at `seen`.
+    pub fn notice(&mut self, seen: Option<(i32, i32)>) {
+        match seen {
+            Some(location) => {
+                self.last_seen = Some(location);
+                self.turns_left = self.memory;
+            }
+            None if self.turns_left > 0 => self.turns_left -= 1,
+            None => self.last_seen = None,
+        }
+    }
+}
+
+/// What using an item does, with the numbers it does it with. `Gear` is a
+/// weapon or armour, worn in its slot until taken off.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
 pub enum Item {
-    Heal,
-    Lightning,
-    Confuse,
+    Heal {
+        amount: i32,
+    },
+    Lightning {
+        damage: i32,
+        range: i32,
+    },
+    Confuse {
+        range: i32,
+        turns: i32,
+    },
+    Gear {
+        slot: Slot,
+        power: i32,
+        defense: i32,
+    },
+}
+
+/// Where gear is worn. The player wears at most one piece in each.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Slot {
+    Weapon,
+    Armour,
+}
+
+/// Which way a flight of stairs leads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stairs {
+    Down,
+    Up,
 }
 
 pub enum UseResult {
     UsedUp,
+    /// Used, but still there to use again.
+    Kept,
     Cancelled,
 }
 
@@ -62,21 +139,32 @@ pub struct Entity {
     pub fighter: Option<Fighter>,
     pub ai: Option<AI>,
     pub item: Option<Item>,
+    pub trap: Option<Trap>,
+    pub senses: Option<Senses>,
+    pub light: Option<Light>,
+    pub stairs: Option<Stairs>,
+    /// Gear the player is wearing, its bonus added to their fighter.
+    pub equipped: bool,
 }
 
 impl Entity {
     pub fn new(x: i32, y: i32, char: char, name: &str, color: Color, blocks: bool) -> Self {
         Entity {
-            x: x,
-            y: y,
-            char: char,
-            color: color,
+            x,
+            y,
+            char,
+            color,
             name: name.into(),
-            blocks: blocks,
+            blocks,
             alive: false,
             fighter: None,
             ai: None,
             item: None,
+            trap: None,
+            senses: None,
+            light: None,
+            stairs: None,
+            equipped: false,
         }
     }
 
@@ -86,12 +174,24 @@ impl Entity {
     }
 
     pub fn get_location(&self) -> (i32, i32) {
-        return (self.x, self.y);
+        (self.x, self.y)
     }
 
-    pub fn draw(&self, console: &mut dyn Console) {
-        console.set_default_foreground(self.color);
-        console.put_char(self.x, self.y, self.char, BackgroundFlag::None);
+    /// A trap the player has not found yet.
+    pub fn hidden(&self) -> bool {
+        self.trap.is_some_and(|trap| trap.hidden)
+    }
+
+    /// The single step, diagonals included, that heads most directly for
+    /// the target.
+    pub fn step_towards(&self, target_x: i32, target_y: i32) -> (i32, i32) {
+        let distance_x = target_x - self.x;
+        let distance_y = target_y - self.y;
+        let distance = ((distance_x.pow(2) + distance_y.pow(2)) as f32).sqrt();
+
+        let step_x = (distance_x as f32 / distance).round() as i32;
+        let step_y = (distance_y as f32 / distance).round() as i32;
+        (step_x, step_y)
     }
 
     pub fn distance_to(&self, other: &Entity) -> f32 {
@@ -148,7 +248,7 @@ impl Entity {
 }
 
 pub fn is_blocked(x: i32, y: i32, map: &Map, entities: &[Entity]) -> bool {
-    if map[x as usize][y as usize].blocked {
+    if map.get(x, y).is_none_or(|tile| tile.blocked()) {
         return true;
     }
 
@@ -157,23 +257,25 @@ pub fn is_blocked(x: i32, y: i32, map: &Map, entities: &[Entity]) -> bool {
         .any(|entity| entity.blocks && entity.get_location() == (x, y))
 }
 
-pub fn move_by(id: usize, x_amount: i32, y_amount: i32, map: &Map, entities: &mut [Entity]) {
+/// Moves unless something is in the way. Returns the trap the entity
+/// stepped onto, if there is one, for the caller to set off.
+pub fn move_by(
+    id: usize,
+    x_amount: i32,
+    y_amount: i32,
+    map: &Map,
+    entities: &mut [Entity],
+) -> Option<usize> {
     let move_x = entities[id].x + x_amount;
     let move_y = entities[id].y + y_amount;
 
-    if !is_blocked(move_x, move_y, map, entities) {
-        entities[id].set_location(move_x, move_y);
+    if is_blocked(move_x, move_y, map, entities) {
+        return None;
     }
-}
-
-pub fn move_towards(id: usize, target_x: i32, target_y: i32, map: &Map, entities: &mut [Entity]) {
-    let distance_x = target_x - entities[id].x;
-    let distance_y = target_y - entities[id].y;
-    let distance = ((distance_x.pow(2) + distance_y.pow(2)) as f32).sqrt();
-
-    let distance_x = (distance_x as f32 / distance).round() as i32;
-    let distance_y = (distance_y as f32 / distance).round() as i32;
-    move_by(id, distance_x, distance_y, map, entities);
+    entities[id].set_location(move_x, move_y);
+    entities
+        .iter()
+        .position(|entity| entity.trap.is_some() && entity.get_location() == (move_x, move_y))
 }
 
 fn player_death(player: &mut Entity, game: &mut Game) {
@@ -0,0 +1,276 @@
+use crate::map::Map;
+
+/// Which tiles of a map can be seen from one spot, worked out by recursive
+/// shadowcasting over the map's own tiles. Only the box the sight radius
+/// reaches is stored, so a short-sighted `Fov` stays small on a big map.
+#[derive(Clone, Debug)]
+pub struct Fov {
+    /// The map coordinates of the box's top left corner.
+    left: i32,
+    top: i32,
+    width: i32,
+    height: i32,
+    visible: Vec<bool>,
+}
+
+/// Turns an octant's (column, row) offsets into map offsets, one row per
+/// octant: `x = col * xx + row * xy`, `y = col * yx + row * yy`.
+const OCTANTS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+impl Fov {
+    /// Nothing visible yet.
+    pub fn new(width: i32, height: i32) -> Self {
+        Fov::covering(0, 0, width, height)
+    }
+
+    fn covering(left: i32, top: i32, width: i32, height: i32) -> Self {
+        let (width, height) = (width.max(0), height.max(0));
+        Fov {
+            left,
+            top,
+            width,
+            height,
+            visible: vec![false; (width * height) as usize],
+        }
+    }
+
+    /// What can be seen from `(x, y)` on `map`, out to `radius` tiles (0 for
+    /// no limit). With `light_walls`, the walls at the edge of sight are
+    /// visible too, not just the open tiles in front of them.
+    pub fn compute(map: &Map, x: i32, y: i32, radius: i32, light_walls: bool) -> Self {
+        let mut fov = if radius > 0 {
+            let (left, top) = ((x - radius).max(0), (y - radius).max(0));
+            let right = (x + radius).min(map.width() - 1);
+            let bottom = (y + radius).min(map.height() - 1);
+            Fov::covering(left, top, right - left + 1, bottom - top + 1)
+        } else {
+            Fov::new(map.width(), map.height())
+        };
+        if !map.in_bounds(x, y) {
+            return fov;
+        }
+        let radius = if radius > 0 {
+            radius
+        } else {
+            map.width() + map.height()
+        };
+
+        fov.set_visible(x, y);
+        let mut caster = Caster {
+            map,
+            fov: &mut fov,
+            origin: (x, y),
+            radius,
+            light_walls,
+        };
+        for &octant in &OCTANTS {
+            caster.cast(1, 1.0, 0.0, octant);
+        }
+        fov
+    }
+
+    /// False off the edge of the map.
+    pub fn is_in_fov(&self, x: i32, y: i32) -> bool {
+        let (x, y) = (x - self.left, y - self.top);
+        x >= 0 && y >= 0 && x < self.width && y < self.height && self.visible[self.index_of(x, y)]
+    }
+
+    /// Hides every visible tile `keep` says no to.
+    pub fn retain(&mut self, mut keep: impl FnMut(i32, i32) -> bool) {
+        for (index, visible) in self.visible.iter_mut().enumerate() {
+            let x = self.left + index as i32 % self.width;
+            let y = self.top + index as i32 / self.width;
+            *visible = *visible && keep(x, y);
+        }
+    }
+
+    fn set_visible(&mut self, x: i32, y: i32) {
+        let index = self.index_of(x - self.left, y - self.top);
+        self.visible[index] = true;
+    }
+
+    fn index_of(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+}
+
+/// Whether someone at `from` who sees out to `radius` tiles can see `to`:
+/// close enough, with nothing in between blocking sight.
+pub fn can_see(map: &Map, from: (i32, i32), to: (i32, i32), radius: i32) -> bool {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    dx * dx + dy * dy <= radius * radius && line_of_sight(map, from, to)
+}
+
+/// Whether the straight line from `from` to `to` is clear. Only the tiles
+/// between them count, so a wall can be seen but not seen through.
+pub fn line_of_sight(map: &Map, (x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> bool {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let (mut x, mut y) = (x0, y0);
+    let mut error = dx + dy;
+    loop {
+        if (x, y) == (x1, y1) {
+            return true;
+        }
+        if (x, y) != (x0, y0) && map.get(x, y).is_none_or(|tile| tile.block_sight()) {
+            return false;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+struct Caster<'a> {
+    map: &'a Map,
+    fov: &'a mut Fov,
+    origin: (i32, i32),
+    radius: i32,
+    light_walls: bool,
+}
+
+impl Caster<'_> {
+    /// Lights one octant from `row` outwards, between the slopes `start` and
+    /// `end`, recursing past each wall that splits the light.
+    fn cast(&mut self, row: i32, mut start: f32, end: f32, (xx, xy, yx, yy): (i32, i32, i32, i32)) {
+        if start < end {
+            return;
+        }
+        let (origin_x, origin_y) = self.origin;
+        let mut next_start = start;
+
+        for distance in row..=self.radius {
+            let mut blocked = false;
+            let dy = -distance;
+            for dx in -distance..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right_slope {
+                    continue;
+                }
+                if end > left_slope {
+                    break;
+                }
+
+                let x = origin_x + dx * xx + dy * xy;
+                let y = origin_y + dx * yx + dy * yy;
+                let opaque = self.map.get(x, y).is_none_or(|tile| tile.block_sight());
+                let in_radius = dx * dx + dy * dy <= self.radius * self.radius;
+                if in_radius && self.map.in_bounds(x, y) && (self.light_walls || !opaque) {
+                    self.fov.set_visible(x, y);
+                }
+
+                if blocked {
+                    if opaque {
+                        next_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if opaque && distance < self.radius {
+                    blocked = true;
+                    self.cast(distance + 1, start, left_slope, (xx, xy, yx, yy));
+                    next_start = right_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Tile;
+    use crate::tile::TileKind;
+
+    /// A walled room with open floor inside.
+    fn room(width: i32, height: i32) -> Map {
+        let mut map = Map::new(width, height, Tile::wall());
+        for x in 1..width - 1 {
+            for y in 1..height - 1 {
+                map.set_kind(x, y, TileKind::Floor);
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn an_open_room_is_all_visible() {
+        let map = room(9, 7);
+        let fov = Fov::compute(&map, 4, 3, 0, true);
+        assert!(map.coords().all(|(x, y)| fov.is_in_fov(x, y)));
+
+        let fov = Fov::compute(&map, 4, 3, 0, false);
+        assert!(map
+            .iter()
+            .all(|((x, y), tile)| fov.is_in_fov(x, y) != tile.block_sight()));
+        assert!(!fov.is_in_fov(-1, 0));
+        assert!(!fov.is_in_fov(9, 0));
+    }
+
+    #[test]
+    fn walls_cast_shadows() {
+        let mut map = room(11, 5);
+        for y in 1..4 {
+            map.set_kind(5, y, TileKind::Wall);
+        }
+        let fov = Fov::compute(&map, 2, 2, 0, true);
+        assert!(fov.is_in_fov(4, 2));
+        assert!(fov.is_in_fov(5, 2));
+        assert!(!fov.is_in_fov(6, 2));
+        assert!(!fov.is_in_fov(8, 1));
+    }
+
+    #[test]
+    fn line_of_sight_needs_a_clear_path() {
+        let mut map = room(11, 5);
+        map.set_kind(5, 2, TileKind::Wall);
+        assert!(line_of_sight(&map, (2, 2), (4, 2)));
+        assert!(line_of_sight(&map, (2, 2), (5, 2)));
+        assert!(!line_of_sight(&map, (2, 2), (8, 2)));
+        assert!(line_of_sight(&map, (2, 1), (8, 1)));
+
+        assert!(can_see(&map, (2, 1), (8, 1), 6));
+        assert!(!can_see(&map, (2, 1), (8, 1), 5));
+        assert!(!can_see(&map, (2, 1), (3, 1), 0));
+    }
+
+    #[test]
+    fn sight_stops_at_the_radius() {
+        let map = room(21, 21);
+        let fov = Fov::compute(&map, 10, 10, 4, true);
+        assert!(fov.is_in_fov(14, 10));
+        assert!(!fov.is_in_fov(15, 10));
+        assert!(fov.is_in_fov(13, 12));
+        assert!(!fov.is_in_fov(14, 14));
+    }
+
+    #[test]
+    fn a_radius_only_covers_the_box_it_reaches() {
+        let map = room(400, 400);
+        let fov = Fov::compute(&map, 2, 10, 4, true);
+        assert_eq!(fov.visible.len(), 7 * 9);
+        assert!(fov.is_in_fov(1, 10));
+        assert!(fov.is_in_fov(6, 10));
+        assert!(!fov.is_in_fov(7, 10));
+        assert!(!fov.is_in_fov(300, 300));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::color::{Color, BLACK, WHITE};
+
+/// A key the game reacts to, whichever frontend read it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+    Enter { alt: bool },
+    Char(char),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Key(Key),
+    /// The mouse moved onto the window cell `(x, y)`.
+    Mouse(i32, i32),
+}
+
+/// What the game needs from whatever shows it to the player: a window of
+/// coloured character cells, and the keys and mouse. The game draws a whole
+/// frame between `clear` and `present`; everything else is built on
+/// `put_char`.
+pub trait Frontend {
+    /// Width and height of the window in cells.
+    fn size(&self) -> (i32, i32);
+
+    fn is_closed(&self) -> bool;
+
+    /// Blanks the window for a new frame.
+    fn clear(&mut self);
+
+    /// Sets the cell at `(x, y)`, keeping its background if `background` is
+    /// `None`. Cells off the window are ignored.
+    fn put_char(
+        &mut self,
+        x: i32,
+        y: i32,
+        glyph: char,
+        foreground: Color,
+        background: Option<Color>,
+    );
+
+    /// Shows the frame drawn since `clear`.
+    fn present(&mut self);
+
+    /// The next key press or mouse move, if there is one, without waiting.
+    fn poll_input(&mut self) -> Option<Input>;
+
+    /// Waits for the player to press a key.
+    fn wait_for_key(&mut self) -> Key;
+
+    fn toggle_fullscreen(&mut self) {}
+
+    /// Writes `text` on one line from `(x, y)` over the current background.
+    fn print(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        for (offset, glyph) in text.chars().enumerate() {
+            self.put_char(x + offset as i32, y, glyph, color, None);
+        }
+    }
+
+    /// Writes `text` on one line centred on `x`.
+    fn print_centered(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        let width = text.chars().count() as i32;
+        self.print(x - width / 2, y, text, color);
+    }
+
+    /// Paints the background of a rectangle of cells, blanking them.
+    fn fill(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color) {
+        for cell_x in x..x + width {
+            for cell_y in y..y + height {
+                self.put_char(cell_x, cell_y, ' ', color, Some(color));
+            }
+        }
+    }
+
+    /// Shows `options`, lettered from (a), under `header` in the middle of
+    /// the current frame and waits for the player to pick one. Any key but
+    /// an option's letter picks nothing.
+    fn menu(&mut self, header: &str, options: &[String], width: i32) -> Option<usize> {
+        assert!(
+            options.len() <= 26,
+            "Cannot have a menu with more than 26 options."
+        );
+
+        let header_lines = wrap(header, width as usize);
+        let height = (header_lines.len() + options.len()) as i32;
+        let (window_width, window_height) = self.size();
+        let x = window_width / 2 - width / 2;
+        let y = window_height / 2 - height / 2;
+
+        self.fill(x, y, width, height, BLACK);
+        for (line_y, line) in header_lines.iter().enumerate() {
+            self.print(x, y + line_y as i32, line, WHITE);
+        }
+        for (index, option_text) in options.iter().enumerate() {
+            let menu_letter = (b'a' + index as u8) as char;
+            let text = format!("({}) {}", menu_letter, option_text);
+            let line_y = y + (header_lines.len() + index) as i32;
+            self.print(x, line_y, &text, WHITE);
+        }
+        self.present();
+
+        match self.wait_for_key() {
+            Key::Char(letter) if letter.is_ascii_alphabetic() => {
+                let index = letter.to_ascii_lowercase() as usize - 'a' as usize;
+                if index < options.len() {
+                    Some(index)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Breaks `text` into lines no wider than `width`, between words where it
+/// can. Every newline in `text` starts a new line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let mut word = word.to_string();
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(line);
+                line = String::new();
+            }
+            while word.chars().count() > width {
+                let rest = word.split_off(word.char_indices().nth(width).unwrap().0);
+                lines.push(word);
+                word = rest;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::Headless;
+
+    #[test]
+    fn wrap_breaks_between_words() {
+        assert_eq!(wrap("a bb ccc dddd", 6), vec!["a bb", "ccc", "dddd"]);
+        assert_eq!(wrap("head\n", 10), vec!["head", ""]);
+        assert_eq!(wrap("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[test]
+    fn menu_letters_pick_options() {
+        let options = vec!["sword".to_string(), "shield".to_string()];
+
+        let mut frontend = Headless::new(20, 6, vec![Key::Char('b')]);
+        assert_eq!(frontend.menu("Pick one", &options, 12), Some(1));
+        assert_eq!(frontend.row(3).trim(), "(a) sword");
+
+        let mut frontend = Headless::new(20, 6, vec![Key::Char('c')]);
+        assert_eq!(frontend.menu("Pick one", &options, 12), None);
+    }
+}
@@ -1,11 +1,48 @@
-use crate::tile::Tile;
-use crate::messages::Messages;
-use crate::entity::Entity;
+use rand::StdRng;
+use std::collections::HashMap;
 
-pub type Map = Vec<Vec<Tile>>;
+use crate::entity::Entity;
+use crate::fov::Fov;
+use crate::items::ItemDef;
+use crate::light::LightMap;
+use crate::map::Map;
+use crate::mapgen::MapGenerator;
+use crate::messages::Messages;
+use crate::monsters::MonsterDef;
+use crate::prefab::Prefab;
 
 pub struct Game {
     pub map: Map,
     pub messages: Messages,
     pub inventory: Vec<Entity>,
+    pub seed: usize,
+    pub rng: StdRng,
+    pub dungeon_level: u32,
+    /// What the player can see, as of the last time it was worked out.
+    pub fov: Fov,
+    /// The light falling on the current level, worked out along with `fov`.
+    pub light: LightMap,
+    /// The levels the player has left, by depth.
+    pub levels: HashMap<u32, LevelSnapshot>,
+    /// Turns left of the player stumbling about after breathing in
+    /// confusion gas.
+    pub player_confusion: i32,
+    /// Set from `--size`; otherwise each level asks the generator.
+    pub map_size: Option<(i32, i32)>,
+    pub generator: Box<dyn MapGenerator>,
+    pub content: Content,
+}
+
+/// A level as the player left it: its map, explored tiles and all, and
+/// everything on it but the player.
+pub struct LevelSnapshot {
+    pub map: Map,
+    pub entities: Vec<Entity>,
+}
+
+/// Everything read from `res/` at startup.
+pub struct Content {
+    pub prefabs: Vec<Prefab>,
+    pub monsters: Vec<MonsterDef>,
+    pub items: Vec<ItemDef>,
 }
@@ -0,0 +1,66 @@
+use crate::color::Color;
+use crate::frontend::{Frontend, Input, Key};
+
+/// A frontend with no window at all, for tests: cells are kept in memory,
+/// every presented frame is recorded as text and keys come from a script.
+/// It counts as closed once the script runs out.
+pub struct Headless {
+    width: i32,
+    height: i32,
+    cells: Vec<char>,
+    /// Still to come, the next key last.
+    keys: Vec<Key>,
+    pub frames: Vec<String>,
+}
+
+impl Headless {
+    pub fn new(width: i32, height: i32, keys: Vec<Key>) -> Self {
+        Headless {
+            width,
+            height,
+            cells: vec![' '; (width * height) as usize],
+            keys: keys.into_iter().rev().collect(),
+            frames: vec![],
+        }
+    }
+
+    pub fn row(&self, y: i32) -> String {
+        let start = (y * self.width) as usize;
+        self.cells[start..start + self.width as usize]
+            .iter()
+            .collect()
+    }
+}
+
+impl Frontend for Headless {
+    fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.cells = vec![' '; (self.width * self.height) as usize];
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, _: Color, _: Option<Color>) {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.cells[(y * self.width + x) as usize] = glyph;
+        }
+    }
+
+    fn present(&mut self) {
+        let rows: Vec<_> = (0..self.height).map(|y| self.row(y)).collect();
+        self.frames.push(rows.join("\n"));
+    }
+
+    fn poll_input(&mut self) -> Option<Input> {
+        self.keys.pop().map(Input::Key)
+    }
+
+    fn wait_for_key(&mut self) -> Key {
+        self.keys.pop().unwrap_or(Key::Escape)
+    }
+}
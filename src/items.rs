@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::entity::{Entity, Item};
+use crate::light::Light;
+use crate::spawn::{self, Transition};
+use crate::themes::{self, RoomTheme};
+
+/// One entry of the items file. Spawning works the same way as for
+/// monsters.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDef {
+    pub name: String,
+    pub glyph: char,
+    pub color: (u8, u8, u8),
+    pub effect: Item,
+    pub spawn_weights: Vec<Transition>,
+    #[serde(default = "spawn::first_level")]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    /// Room themes this item is especially likely to turn up in.
+    #[serde(default)]
+    pub themes: Vec<RoomTheme>,
+    /// Light the item gives off while it lies on the floor.
+    #[serde(default)]
+    pub light: Option<Light>,
+}
+
+impl ItemDef {
+    pub fn spawn(&self, x: i32, y: i32) -> Entity {
+        let (r, g, b) = self.color;
+        let mut item = Entity::new(x, y, self.glyph, &self.name, Color::new(r, g, b), false);
+        item.item = Some(self.effect);
+        item.light = self.light;
+        item
+    }
+
+    /// `spawn_weights` with the depth range folded in and boosted for a
+    /// favoured room theme, ready for a spawn table.
+    pub fn weights(&self, theme: Option<RoomTheme>) -> Vec<Transition> {
+        let weights = spawn::depth_limited(&self.spawn_weights, self.min_depth, self.max_depth);
+        themes::favour(weights, &self.themes, theme)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is empty".into());
+        }
+        if self.glyph.is_whitespace() {
+            return Err("glyph cannot be blank".into());
+        }
+        let parameters_positive = match self.effect {
+            Item::Heal { amount } => amount > 0,
+            Item::Lightning { damage, range } => damage > 0 && range > 0,
+            Item::Confuse { range, turns } => range > 0 && turns > 0,
+            Item::Gear { power, defense, .. } => power >= 0 && defense >= 0 && power + defense > 0,
+        };
+        if !parameters_positive {
+            return Err(format!(
+                "effect parameters must be above 0, got {:?}",
+                self.effect
+            ));
+        }
+        if let Some(light) = self.light {
+            light.validate()?;
+        }
+        spawn::check_weights(&self.spawn_weights, self.min_depth, self.max_depth)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<ItemDef>, String> {
+    let items: Vec<ItemDef> = ron::from_str(text).map_err(|error| error.to_string())?;
+    if items.is_empty() {
+        return Err("no items defined".into());
+    }
+
+    let mut names = HashSet::new();
+    for (index, item) in items.iter().enumerate() {
+        item.validate()
+            .map_err(|error| format!("item {} '{}': {}", index + 1, item.name, error))?;
+        if !names.insert(&item.name) {
+            return Err(format!(
+                "item {} '{}': name is already taken",
+                index + 1,
+                item.name
+            ));
+        }
+    }
+    Ok(items)
+}
+
+pub fn load(path: &Path) -> Result<Vec<ItemDef>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+    parse(&text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOLT: &str = r#"[(
+        name: "scroll of long-range lightning",
+        glyph: '#',
+        color: (255, 255, 63),
+        effect: Lightning(damage: 30, range: 12),
+        spawn_weights: [(level: 3, value: 10)],
+    )]"#;
+
+    #[test]
+    fn shipped_items_load() {
+        let items = load(Path::new("res/items.ron")).unwrap();
+        assert!(items.iter().any(|item| item.name == "healing potion"));
+    }
+
+    #[test]
+    fn parse_reads_effect_parameters() {
+        let bolt = &parse(BOLT).unwrap()[0];
+        assert_eq!(
+            bolt.effect,
+            Item::Lightning {
+                damage: 30,
+                range: 12
+            }
+        );
+        assert_eq!(bolt.min_depth, 1);
+        assert_eq!(bolt.spawn(4, 5).item, Some(bolt.effect));
+    }
+
+    #[test]
+    fn parse_rejects_bad_entries() {
+        let error = parse(&BOLT.replace("range: 12", "range: 0")).unwrap_err();
+        assert_eq!(
+            error,
+            "item 1 'scroll of long-range lightning': effect parameters must be above 0, \
+             got Lightning { damage: 30, range: 0 }"
+        );
+        assert!(parse(&BOLT.replace("Lightning(", "Fireball(")).is_err());
+        assert!(parse(&BOLT.replace("range: 12", "radius: 12")).is_err());
+    }
+}
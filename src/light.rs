@@ -0,0 +1,213 @@
+use serde::Deserialize;
+
+use crate::color::Color;
+use crate::fov::Fov;
+use crate::map::Map;
+use crate::tile::TileKind;
+
+/// Below this brightness a tile is too dark to make anything out.
+pub const MIN_VISIBLE_LIGHT: f32 = 0.05;
+
+/// How bright a lit room is all over.
+const ROOM_LIGHT: [f32; 3] = [0.75, 0.75, 0.7];
+
+pub const WALL_TORCH: Light = Light {
+    radius: 5,
+    color: (255, 150, 60),
+    falloff: Falloff::Quadratic,
+};
+
+/// How a light dims on its way out to its radius.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Falloff {
+    Flat,
+    Linear,
+    Quadratic,
+}
+
+/// Light given off by an entity or a tile.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Light {
+    pub radius: i32,
+    pub color: (u8, u8, u8),
+    pub falloff: Falloff,
+}
+
+impl Light {
+    /// Share of the full colour reaching `distance` tiles away.
+    fn intensity_at(self, distance: f32) -> f32 {
+        let remaining = 1.0 - distance / (self.radius + 1) as f32;
+        match self.falloff {
+            Falloff::Flat => 1.0,
+            Falloff::Linear => remaining,
+            Falloff::Quadratic => remaining * remaining,
+        }
+    }
+
+    pub fn validate(self) -> Result<(), String> {
+        if self.radius <= 0 {
+            return Err(format!("light radius must be above 0, got {}", self.radius));
+        }
+        Ok(())
+    }
+}
+
+/// How much red, green and blue light falls on each tile of a map. Lights
+/// add up and can go past 1.
+#[derive(Clone, Debug)]
+pub struct LightMap {
+    width: i32,
+    height: i32,
+    levels: Vec<[f32; 3]>,
+}
+
+impl LightMap {
+    /// No light anywhere.
+    pub fn dark(width: i32, height: i32) -> Self {
+        LightMap {
+            width,
+            height,
+            levels: vec![[0.0; 3]; (width * height) as usize],
+        }
+    }
+
+    /// The light on `map` from its lit rooms, its wall torches and `sources`.
+    /// Walls stop light just as they stop sight.
+    pub fn compute(map: &Map, sources: &[((i32, i32), Light)]) -> Self {
+        let mut light_map = LightMap::dark(map.width(), map.height());
+        for ((x, y), tile) in map.iter() {
+            if tile.lit() {
+                light_map.add(x, y, ROOM_LIGHT);
+            }
+        }
+
+        let torches = map
+            .iter()
+            .filter(|(_, tile)| tile.kind() == TileKind::WallTorch)
+            .map(|(location, _)| (location, WALL_TORCH));
+        for ((source_x, source_y), light) in torches.chain(sources.iter().cloned()) {
+            let reach = Fov::compute(map, source_x, source_y, light.radius, true);
+            let (r, g, b) = light.color;
+            for x in (source_x - light.radius)..=(source_x + light.radius) {
+                for y in (source_y - light.radius)..=(source_y + light.radius) {
+                    if !reach.is_in_fov(x, y) {
+                        continue;
+                    }
+                    let distance = (((x - source_x).pow(2) + (y - source_y).pow(2)) as f32).sqrt();
+                    let intensity = light.intensity_at(distance) / 255.0;
+                    let color = [r, g, b].map(|channel| channel as f32 * intensity);
+                    light_map.add(x, y, color);
+                }
+            }
+        }
+        light_map
+    }
+
+    /// No light at all, off the edge of the map too.
+    pub fn at(&self, x: i32, y: i32) -> [f32; 3] {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.levels[(y * self.width + x) as usize]
+        } else {
+            [0.0; 3]
+        }
+    }
+
+    /// The strongest of the three channels.
+    pub fn brightness(&self, x: i32, y: i32) -> f32 {
+        let [r, g, b] = self.at(x, y);
+        r.max(g).max(b)
+    }
+
+    pub fn is_lit(&self, x: i32, y: i32) -> bool {
+        self.brightness(x, y) >= MIN_VISIBLE_LIGHT
+    }
+
+    fn add(&mut self, x: i32, y: i32, color: [f32; 3]) {
+        let level = &mut self.levels[(y * self.width + x) as usize];
+        for (channel, added) in level.iter_mut().zip(&color) {
+            *channel += added;
+        }
+    }
+}
+
+/// `bright` as it looks under `light`, but never darker than `dark`.
+pub fn shade(dark: Color, bright: Color, light: [f32; 3]) -> Color {
+    let channel =
+        |dark: u8, bright: u8, light: f32| (bright as f32 * light.min(1.0)).max(dark as f32) as u8;
+    Color::new(
+        channel(dark.r, bright.r, light[0]),
+        channel(dark.g, bright.g, light[1]),
+        channel(dark.b, bright.b, light[2]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Tile;
+
+    fn open_map(width: i32, height: i32) -> Map {
+        let mut map = Map::new(width, height, Tile::wall());
+        for x in 1..width - 1 {
+            for y in 1..height - 1 {
+                map.set_kind(x, y, TileKind::Floor);
+            }
+        }
+        map
+    }
+
+    const LAMP: Light = Light {
+        radius: 3,
+        color: (255, 0, 0),
+        falloff: Falloff::Linear,
+    };
+
+    #[test]
+    fn light_fades_out_to_its_radius() {
+        let map = open_map(12, 12);
+        let light_map = LightMap::compute(&map, &[((5, 5), LAMP)]);
+
+        assert_eq!(light_map.at(5, 5), [1.0, 0.0, 0.0]);
+        assert!(light_map.brightness(6, 5) < 1.0);
+        assert!(light_map.brightness(7, 5) < light_map.brightness(6, 5));
+        assert!(light_map.is_lit(8, 5));
+        assert!(!light_map.is_lit(9, 5));
+        assert_eq!(light_map.at(-1, 5), [0.0; 3]);
+    }
+
+    #[test]
+    fn walls_stop_light_and_lit_rooms_glow() {
+        let mut map = open_map(12, 5);
+        for y in 1..4 {
+            map.set_kind(4, y, TileKind::Wall);
+        }
+        map.set_lit(8, 2, true);
+        let light_map = LightMap::compute(&map, &[((2, 2), LAMP)]);
+
+        assert!(light_map.is_lit(4, 2));
+        assert!(!light_map.is_lit(5, 2));
+        assert_eq!(light_map.at(8, 2), ROOM_LIGHT);
+    }
+
+    #[test]
+    fn wall_torches_light_their_surroundings() {
+        let mut map = open_map(12, 12);
+        map.set_kind(0, 5, TileKind::WallTorch);
+        let light_map = LightMap::compute(&map, &[]);
+
+        assert!(light_map.is_lit(1, 5));
+        assert!(!light_map.is_lit(10, 5));
+    }
+
+    #[test]
+    fn shade_never_goes_below_dark() {
+        let dark = Color::new(10, 10, 100);
+        let bright = Color::new(200, 200, 200);
+        assert_eq!(shade(dark, bright, [0.0; 3]), dark);
+        assert_eq!(
+            shade(dark, bright, [1.0, 0.5, 2.0]),
+            Color::new(200, 100, 200)
+        );
+    }
+}
@@ -1,89 +1,132 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng, StdRng};
 use std::cmp;
-use tcod::colors::*;
-use tcod::console::*;
-use tcod::input::{self, Event, Key, Mouse};
-use tcod::map::{FovAlgorithm, Map as FovMap};
+use std::collections::HashMap;
+use std::env;
+use std::mem;
+use std::path::Path;
+use std::process;
+
+mod color;
+use color::*;
+
+mod frontend;
+use frontend::{Frontend, Input, Key};
+
+#[cfg(feature = "tcod")]
+mod tcod_frontend;
+#[cfg(feature = "tcod")]
+use tcod_frontend::TcodFrontend;
+
+mod terminal_frontend;
+use terminal_frontend::TerminalFrontend;
+
+#[cfg(test)]
+mod headless;
 
 mod entity;
-use entity::Entity;
+use entity::{Entity, Stairs};
 
 pub mod tile;
-use tile::Tile;
 
 pub mod game;
-use game::Game;
-use game::Map;
+use game::{Content, Game, LevelSnapshot};
+
+pub mod map;
+use map::Map;
+use tile::TileKind;
+
+mod fov;
+use fov::Fov;
+
+mod light;
+use light::{Falloff, Light, LightMap};
 
 mod rect;
-use rect::Rect;
+
+mod camera;
+use camera::Camera;
+
+mod mapgen;
+use mapgen::{MapGenerator, SpawnArea};
+
+mod prefab;
+use prefab::Prefab;
 
 mod messages;
 use messages::Messages;
 
+mod spawn;
+use spawn::{SpawnTable, Transition};
+
+mod monsters;
+
+mod items;
+
+mod trap;
+use trap::TrapKind;
+
+mod themes;
+use themes::RoomTheme;
+
+#[cfg(feature = "tcod")]
 const WINDOW_WIDTH: i32 = 80;
+#[cfg(feature = "tcod")]
 const WINDOW_HEIGHT: i32 = 50;
 
-const MAP_WIDTH: i32 = 80;
-const MAP_HEIGHT: i32 = 43;
+/// The frontends this build can use, the default first.
+#[cfg(feature = "tcod")]
+const FRONTENDS: &[&str] = &["tcod", "terminal"];
+#[cfg(not(feature = "tcod"))]
+const FRONTENDS: &[&str] = &["terminal"];
 
+// The map view takes up the window above the panel, whatever size it is.
 const BAR_WIDTH: i32 = 20;
 const PANEL_HEIGHT: i32 = 7;
-const PANEL_Y: i32 = WINDOW_HEIGHT - PANEL_HEIGHT;
 
 const MSG_X: i32 = BAR_WIDTH + 2;
-const MSG_WIDTH: i32 = WINDOW_WIDTH - BAR_WIDTH - 2;
 const MSG_HEIGHT: usize = PANEL_HEIGHT as usize - 1;
 
-const ROOM_MAX_SIZE: i32 = 10;
-const ROOM_MIN_SIZE: i32 = 6;
-const MAX_ROOMS: i32 = 30;
+const PREFAB_DIR: &str = "res/prefabs";
+const MONSTERS_FILE: &str = "res/monsters.ron";
+const ITEMS_FILE: &str = "res/items.ron";
 
-const MAX_ROOM_MONSTERS: i32 = 3;
-const MAX_ROOM_ITEMS: i32 = 2;
+const MAX_ROOM_MONSTERS: &[Transition] = &[
+    Transition { level: 1, value: 2 },
+    Transition { level: 4, value: 3 },
+    Transition { level: 6, value: 5 },
+];
+const MAX_ROOM_ITEMS: &[Transition] = &[
+    Transition { level: 1, value: 1 },
+    Transition { level: 4, value: 2 },
+];
 
 const INVENTORY_WIDTH: i32 = 50;
 
-const HEAL_AMOUNT: i32 = 4;
+const DART_DAMAGE: i32 = 4;
+const ALARM_RADIUS: f32 = 15.0;
+const ALARM_NUM_TURNS: i32 = 20;
+const GAS_CONFUSE_NUM_TURNS: i32 = 5;
 
-const LIGHTNING_DAMAGE: i32 = 40;
-const LIGHTNING_RANGE: i32 = 5;
+const SEARCH_RADIUS: i32 = 2;
+const SEARCH_CHANCE: f32 = 0.5;
 
-const CONFUSE_RANGE: i32 = 8;
-const CONFUSE_NUM_TURNS: i32 = 10;
-
-const FOV_ALGO: FovAlgorithm = FovAlgorithm::Basic;
 const FOV_LIGHT_WALLS: bool = true;
 const TORCH_RADIUS: i32 = 10;
-
-const COLOR_DARK_WALL: Color = Color { r: 0, g: 0, b: 100 };
-const COLOR_LIGHT_WALL: Color = Color {
-    r: 130,
-    g: 110,
-    b: 50,
-};
-const COLOR_DARK_GROUND: Color = Color {
-    r: 50,
-    g: 50,
-    b: 150,
-};
-const COLOR_LIGHT_GROUND: Color = Color {
-    r: 200,
-    g: 180,
-    b: 50,
+const PLAYER_TORCH: Light = Light {
+    radius: TORCH_RADIUS,
+    color: (255, 220, 170),
+    falloff: Falloff::Linear,
 };
 
 const LIMIT_FPS: i32 = 20;
 
 const PLAYER_ID: usize = 0;
 
-struct Tcod {
-    root: Root,
-    console: Offscreen,
-    panel: Offscreen,
-    fov: FovMap,
-    key: Key,
-    mouse: Mouse,
+/// What the game keeps about the screen between frames.
+struct Ui {
+    camera: Camera,
+    /// The window cell the mouse is over, once it has moved.
+    mouse: Option<(i32, i32)>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -93,44 +136,40 @@ enum PlayerAction {
     Exit,
 }
 
-fn handle_key_input(tcod: &mut Tcod, entities: &mut Vec<Entity>, game: &mut Game) -> PlayerAction {
-    use tcod::input::KeyCode::*;
+fn handle_key_input(
+    key: Key,
+    frontend: &mut dyn Frontend,
+    entities: &mut Vec<Entity>,
+    game: &mut Game,
+) -> PlayerAction {
+    use Key::*;
 
     use PlayerAction::*;
 
     let player_alive = entities[PLAYER_ID].alive;
-    match (tcod.key, tcod.key.text(), player_alive) {
-        (
-            Key {
-                code: Enter,
-                alt: true,
-                ..
-            },
-            _,
-            _,
-        ) => {
-            let fullscreen_state = tcod.root.is_fullscreen();
-            tcod.root.set_fullscreen(!fullscreen_state);
+    match (key, player_alive) {
+        (Enter { alt: true }, _) => {
+            frontend.toggle_fullscreen();
             DidntTakeTurn
         }
-        (Key { code: Escape, .. }, _, _) => Exit,
-        (Key { code: Up, .. }, _, true) => {
+        (Escape, _) => Exit,
+        (Up, true) => {
             player_move_or_attack(0, -1, game, entities);
             TookTurn
         }
-        (Key { code: Down, .. }, _, true) => {
+        (Down, true) => {
             player_move_or_attack(0, 1, game, entities);
             TookTurn
         }
-        (Key { code: Left, .. }, _, true) => {
+        (Left, true) => {
             player_move_or_attack(-1, 0, game, entities);
             TookTurn
         }
-        (Key { code: Right, .. }, _, true) => {
+        (Right, true) => {
             player_move_or_attack(1, 0, game, entities);
             TookTurn
         }
-        (Key { code: Text, .. }, "g", true) => {
+        (Char('g'), true) => {
             let item_id = entities.iter().position(|entity| {
                 entity.get_location() == entities[PLAYER_ID].get_location() && entity.item.is_some()
             });
@@ -139,17 +178,60 @@ fn handle_key_input(tcod: &mut Tcod, entities: &mut Vec<Entity>, game: &mut Game
             }
             DidntTakeTurn
         }
-        (Key { code: Text, .. }, "i", true) => {
-            let original_inventory_length = &game.inventory.len();
+        (Char('i'), true) => {
             let inventory_index = inventory_menu(
                 &game.inventory,
                 "Press the key next to an item to use it, or any other to cancel.\n",
-                &mut tcod.root,
+                frontend,
             );
-            if let Some(inventory_index) = inventory_index {
-                use_item(inventory_index, tcod, game, entities);
+            match inventory_index {
+                Some(inventory_index) if use_item(inventory_index, game, entities) => TookTurn,
+                _ => DidntTakeTurn,
+            }
+        }
+        (Char('o'), true) => match adjacent_door(TileKind::ClosedDoor, game, entities) {
+            Some((x, y)) => {
+                toggle_door(x, y, game);
+                game.messages.add("You open the door.", WHITE);
+                TookTurn
+            }
+            None => {
+                game.messages
+                    .add("There is no closed door next to you.", WHITE);
+                DidntTakeTurn
+            }
+        },
+        (Char('c'), true) => match adjacent_door(TileKind::OpenDoor, game, entities) {
+            Some((x, y)) => {
+                toggle_door(x, y, game);
+                game.messages.add("You close the door.", WHITE);
+                TookTurn
+            }
+            None => {
+                game.messages
+                    .add("There is no open door you can close next to you.", WHITE);
+                DidntTakeTurn
+            }
+        },
+        (Char('s'), true) => {
+            search(game, entities);
+            TookTurn
+        }
+        (Char('>'), true) => {
+            if player_on(entities, Stairs::Down) {
+                game.messages
+                    .add("You descend deeper into the heart of the dungeon...", RED);
+                change_level(game.dungeon_level + 1, game, entities);
+                TookTurn
+            } else {
+                DidntTakeTurn
             }
-            if original_inventory_length > &game.inventory.len() {
+        }
+        (Char('<'), true) => {
+            if player_on(entities, Stairs::Up) {
+                game.messages
+                    .add("You climb back up the stairs...", LIGHT_VIOLET);
+                change_level(game.dungeon_level - 1, game, entities);
                 TookTurn
             } else {
                 DidntTakeTurn
@@ -160,126 +242,241 @@ fn handle_key_input(tcod: &mut Tcod, entities: &mut Vec<Entity>, game: &mut Game
     }
 }
 
-fn make_map(entities: &mut Vec<Entity>) -> Map {
-    let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+fn make_level(
+    generator: &dyn MapGenerator,
+    content: &Content,
+    (width, height): (i32, i32),
+    dungeon_level: u32,
+    entities: &mut Vec<Entity>,
+    rng: &mut StdRng,
+) -> Map {
+    let level = mapgen::build_level(generator, &content.prefabs, width, height, rng);
+
+    let (player_x, player_y) = level.player_start;
+    entities[PLAYER_ID].set_location(player_x, player_y);
+
+    for &((x, y), feature) in &level.features {
+        entities.push(feature.make(x, y));
+    }
 
-    let mut rooms = vec![];
+    for area in &level.spawn_areas {
+        place_entities(area, dungeon_level, content, &level.map, entities, rng);
+    }
 
-    for _ in 0..MAX_ROOMS {
-        let w = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let h = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let x = rand::thread_rng().gen_range(0, MAP_WIDTH - w);
-        let y = rand::thread_rng().gen_range(0, MAP_HEIGHT - h);
+    for &(x, y) in &level.monster_spots {
+        if !entity::is_blocked(x, y, &level.map, entities) {
+            entities.extend(make_monster(x, y, dungeon_level, None, content, rng));
+        }
+    }
 
-        let new_room = Rect::new(x, y, w, h);
+    for &(x, y) in &level.item_spots {
+        entities.extend(make_item(x, y, dungeon_level, None, content, rng));
+    }
 
-        let rooms_intersect = rooms
-            .iter()
-            .any(|other_room| new_room.intersects_with(other_room));
+    let (stairs_x, stairs_y) = level.stairs;
+    let mut downstairs = Entity::new(stairs_x, stairs_y, '>', "downstairs", WHITE, false);
+    downstairs.stairs = Some(Stairs::Down);
+    entities.push(downstairs);
+    if dungeon_level > 1 {
+        let mut upstairs = Entity::new(player_x, player_y, '<', "upstairs", WHITE, false);
+        upstairs.stairs = Some(Stairs::Up);
+        entities.push(upstairs);
+    }
 
-        if !rooms_intersect {
-            create_room(new_room, &mut map);
-            place_entities(new_room, &map, entities);
+    let taken: Vec<_> = entities.iter().map(Entity::get_location).collect();
+    let num_traps = spawn::from_dungeon_level(trap::MAX_LEVEL_TRAPS, dungeon_level);
+    for (x, y) in trap::trap_spots(&level.map, &taken, num_traps, rng) {
+        if let Some(kind) = trap::trap_table().pick(dungeon_level, rng) {
+            entities.push(trap::make_trap(x, y, kind));
+        }
+    }
 
-            let (new_x, new_y) = new_room.center();
+    level.map
+}
 
-            if rooms.is_empty() {
-                entities[PLAYER_ID].set_location(new_x, new_y);
-            } else {
-                let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
-                if rand::random() {
-                    create_horizontal_tunnel(prev_x, new_x, prev_y, &mut map);
-                    create_vertical_tunnel(prev_y, new_y, new_x, &mut map);
-                } else {
-                    create_vertical_tunnel(prev_y, new_y, prev_x, &mut map);
-                    create_horizontal_tunnel(prev_x, new_x, new_y, &mut map);
-                }
-            }
+fn player_on(entities: &[Entity], stairs: Stairs) -> bool {
+    let location = entities[PLAYER_ID].get_location();
+    entities
+        .iter()
+        .any(|entity| entity.get_location() == location && entity.stairs == Some(stairs))
+}
+
+/// `(x, y)` if nothing stands there, otherwise the nearest tile around it
+/// that is free, looking a ring further out at a time.
+fn free_tile_near(x: i32, y: i32, map: &Map, entities: &[Entity]) -> (i32, i32) {
+    let max_radius = cmp::max(map.width(), map.height());
+    (0..max_radius)
+        .flat_map(|radius| {
+            (-radius..=radius)
+                .flat_map(move |dx| (-radius..=radius).map(move |dy| (dx, dy)))
+                .filter(move |&(dx, dy)| cmp::max(dx.abs(), dy.abs()) == radius)
+        })
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .find(|&(x, y)| !entity::is_blocked(x, y, map, entities))
+        .unwrap_or((x, y))
+}
+
+/// Moves the player to `depth`, putting the level they leave away in
+/// `game.levels`. A level seen before comes back just as it was left; a new
+/// one is generated.
+fn change_level(depth: u32, game: &mut Game, entities: &mut Vec<Entity>) {
+    let going_down = depth > game.dungeon_level;
+    let level_entities = entities.split_off(PLAYER_ID + 1);
+
+    let map = match game.levels.remove(&depth) {
+        Some(snapshot) => {
+            entities.extend(snapshot.entities);
+            snapshot.map
         }
+        None => {
+            let size = game
+                .map_size
+                .unwrap_or_else(|| game.generator.map_size(depth));
+            make_level(
+                game.generator.as_ref(),
+                &game.content,
+                size,
+                depth,
+                entities,
+                &mut game.rng,
+            )
+        }
+    };
+    let old_map = mem::replace(&mut game.map, map);
+    game.levels.insert(
+        game.dungeon_level,
+        LevelSnapshot {
+            map: old_map,
+            entities: level_entities,
+        },
+    );
+    game.dungeon_level = depth;
 
-        rooms.push(new_room);
+    // Arrive on the stairs leading back the way the player came, or beside
+    // them if a monster is standing there.
+    let arrival = if going_down { Stairs::Up } else { Stairs::Down };
+    let stairs = entities
+        .iter()
+        .find(|entity| entity.stairs == Some(arrival))
+        .map(Entity::get_location);
+    if let Some((x, y)) = stairs {
+        let (x, y) = free_tile_near(x, y, &game.map, &entities[PLAYER_ID + 1..]);
+        entities[PLAYER_ID].set_location(x, y);
     }
 
-    map
+    update_fov(game, entities);
 }
 
-fn render_all(tcod: &mut Tcod, game: &mut Game, entities: &[Entity], fov_recompute: bool) {
-    if fov_recompute {
-        let player_location = &entities[PLAYER_ID].get_location();
+/// Works out the light on the level, then what the player can see by it.
+fn update_fov(game: &mut Game, entities: &[Entity]) {
+    game.map.take_changes();
+    game.light = LightMap::compute(&game.map, &light_sources(entities));
 
-        tcod.fov.compute_fov(
-            player_location.0,
-            player_location.1,
-            TORCH_RADIUS,
-            FOV_LIGHT_WALLS,
-            FOV_ALGO,
-        );
+    let (player_x, player_y) = entities[PLAYER_ID].get_location();
+    let mut fov = Fov::compute(&game.map, player_x, player_y, 0, FOV_LIGHT_WALLS);
+    fov.retain(|x, y| game.light.is_lit(x, y));
+    game.fov = fov;
+}
+
+/// Every light carried by or lying on the level, and where it is.
+fn light_sources(entities: &[Entity]) -> Vec<((i32, i32), Light)> {
+    entities
+        .iter()
+        .filter(|entity| !entity.hidden())
+        .filter_map(|entity| entity.light.map(|light| (entity.get_location(), light)))
+        .collect()
+}
+
+fn render_all(
+    frontend: &mut dyn Frontend,
+    ui: &mut Ui,
+    game: &mut Game,
+    entities: &[Entity],
+    fov_recompute: bool,
+) {
+    if fov_recompute || game.map.take_changes() {
+        update_fov(game, entities);
     }
 
-    for y in 0..MAP_HEIGHT {
-        for x in 0..MAP_WIDTH {
-            let visible = tcod.fov.is_in_fov(x, y);
-            let wall = game.map[x as usize][y as usize].block_sight;
-            let color = match (visible, wall) {
-                (false, true) => COLOR_DARK_WALL,
-                (false, false) => COLOR_DARK_GROUND,
-                (true, true) => COLOR_LIGHT_WALL,
-                (true, false) => COLOR_LIGHT_GROUND,
-            };
+    let (window_width, window_height) = frontend.size();
+    let panel_y = window_height - PANEL_HEIGHT;
+    ui.camera.width = window_width;
+    ui.camera.height = panel_y;
 
-            let explored = &mut game.map[x as usize][y as usize].explored;
+    let (player_x, player_y) = entities[PLAYER_ID].get_location();
+    ui.camera
+        .follow(player_x, player_y, game.map.width(), game.map.height());
 
-            if visible {
-                *explored = true;
-            }
-            if *explored {
-                tcod.console
-                    .set_char_background(x, y, color, BackgroundFlag::Set);
+    for ((x, y), kind, memory) in game.map.iter_memory_mut() {
+        let visible = game.fov.is_in_fov(x, y);
+        let terrain = kind.terrain();
+
+        if visible {
+            memory.explored = true;
+            // Filled in again below from whatever is here now.
+            memory.remembered = None;
+        }
+        if memory.explored {
+            if let Some((screen_x, screen_y)) = ui.camera.to_screen(x, y) {
+                let (glyph, foreground, background) = if visible {
+                    let light = game.light.at(x, y);
+                    let dimmed = lerp(terrain.glyph_color, terrain.dark, 0.6);
+                    (
+                        terrain.glyph,
+                        light::shade(dimmed, terrain.glyph_color, light),
+                        light::shade(terrain.dark, terrain.light, light),
+                    )
+                } else if let Some((glyph, color)) = memory.remembered {
+                    (glyph, lerp(color, terrain.dark, 0.6), terrain.dark)
+                } else {
+                    (
+                        terrain.glyph,
+                        lerp(terrain.glyph_color, terrain.dark, 0.6),
+                        terrain.dark,
+                    )
+                };
+                frontend.put_char(screen_x, screen_y, glyph, foreground, Some(background));
             }
         }
     }
 
     let mut to_draw: Vec<_> = entities
         .iter()
-        .filter(|o| tcod.fov.is_in_fov(o.x, o.y))
+        .enumerate()
+        .filter(|(_, o)| game.fov.is_in_fov(o.x, o.y) && !o.hidden())
         .collect();
 
-    to_draw.sort_by(|o1, o2| o1.blocks.cmp(&o2.blocks));
+    to_draw.sort_by_key(|(_, o)| o.blocks);
 
-    for entity in &to_draw {
-        entity.draw(&mut tcod.console);
+    for &(id, entity) in &to_draw {
+        if id != PLAYER_ID {
+            if let Some(memory) = game.map.memory_mut(entity.x, entity.y) {
+                memory.remembered = Some((entity.char, entity.color));
+            }
+        }
+        if let Some((screen_x, screen_y)) = ui.camera.to_screen(entity.x, entity.y) {
+            frontend.put_char(screen_x, screen_y, entity.char, entity.color, None);
+        }
     }
 
-    blit(
-        &tcod.console,
-        (0, 0),
-        (MAP_WIDTH, MAP_HEIGHT),
-        &mut tcod.root,
-        (0, 0),
-        1.0,
-        1.0,
-    );
-
-    tcod.panel.set_default_background(BLACK);
-    tcod.panel.clear();
-
     let mut y = MSG_HEIGHT as i32;
     for &(ref msg, color) in game.messages.iter().rev() {
-        let msg_height = tcod.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, msg);
-        y -= msg_height;
+        let lines = frontend::wrap(msg, (window_width - MSG_X).max(1) as usize);
+        y -= lines.len() as i32;
         if y < 0 {
             break;
         }
-        tcod.panel.set_default_foreground(color);
-        tcod.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+        for (line_y, line) in lines.iter().enumerate() {
+            frontend.print(MSG_X, panel_y + y + line_y as i32, line, color);
+        }
     }
 
     let hp = entities[PLAYER_ID].fighter.map_or(0, |f| f.hp);
     let max_hp = entities[PLAYER_ID].fighter.map_or(0, |f| f.max_hp);
     render_bar(
-        &mut tcod.panel,
-        1,
+        frontend,
         1,
+        panel_y + 1,
         BAR_WIDTH,
         "HP",
         hp,
@@ -288,131 +485,272 @@ fn render_all(tcod: &mut Tcod, game: &mut Game, entities: &[Entity], fov_recompu
         DARKER_RED,
     );
 
-    tcod.panel.set_default_foreground(LIGHT_GREY);
-    tcod.panel.print_ex(
-        1,
-        0,
-        BackgroundFlag::None,
-        TextAlignment::Left,
-        get_names_under_mouse(tcod.mouse, entities, &tcod.fov),
-    );
-
-    blit(
-        &tcod.panel,
-        (0, 0),
-        (WINDOW_WIDTH, PANEL_HEIGHT),
-        &mut tcod.root,
-        (0, PANEL_Y),
-        1.0,
-        1.0,
-    );
-}
-
-fn create_room(room: Rect, map: &mut Map) {
-    for x in (room.x1 + 1)..room.x2 {
-        for y in (room.y1 + 1)..room.y2 {
-            map[x as usize][y as usize] = Tile::empty();
-        }
-    }
-}
-
-fn create_horizontal_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map) {
-    for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
-        map[x as usize][y as usize] = Tile::empty();
-    }
-}
+    let level = format!("Dungeon level: {}", game.dungeon_level);
+    frontend.print(1, panel_y + 3, &level, WHITE);
 
-fn create_vertical_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map) {
-    for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
-        map[x as usize][y as usize] = Tile::empty();
-    }
+    let names = get_names_under_mouse(ui.mouse, ui.camera, entities, &game.fov);
+    frontend.print(1, panel_y, &names, LIGHT_GREY);
 }
 
-fn place_entities(room: Rect, map: &Map, entities: &mut Vec<Entity>) {
-    let num_monsters = rand::thread_rng().gen_range(0, MAX_ROOM_MONSTERS + 1);
+fn place_entities(
+    area: &SpawnArea,
+    dungeon_level: u32,
+    content: &Content,
+    map: &Map,
+    entities: &mut Vec<Entity>,
+    rng: &mut StdRng,
+) {
+    let spots = &area.tiles;
+    let max_monsters = spawn::from_dungeon_level(MAX_ROOM_MONSTERS, dungeon_level)
+        + area.theme.map_or(0, RoomTheme::extra_monsters);
+    let num_monsters = rng.gen_range(0, max_monsters + 1);
 
     for _ in 0..num_monsters {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+        let (x, y) = spots[rng.gen_range(0, spots.len())];
 
         if !entity::is_blocked(x, y, map, entities) {
-            let mut monster = if rand::random::<f32>() < 0.8 {
-                let mut orc = Entity::new(x, y, 'o', "orc", DESATURATED_GREEN, true);
-                orc.fighter = Some(entity::Fighter {
-                    max_hp: 10,
-                    hp: 10,
-                    defense: 0,
-                    power: 3,
-                    on_death: entity::DeathCallback::Monster,
-                });
-                orc.ai = Some(entity::AI::Basic);
-                orc
-            } else {
-                let mut troll = Entity::new(x, y, 'T', "troll", DARKER_GREEN, true);
-                troll.fighter = Some(entity::Fighter {
-                    max_hp: 16,
-                    hp: 16,
-                    defense: 1,
-                    power: 4,
-                    on_death: entity::DeathCallback::Monster,
-                });
-                troll.ai = Some(entity::AI::Basic);
-                troll
-            };
-            monster.alive = true;
-            entities.push(monster);
+            entities.extend(make_monster(x, y, dungeon_level, area.theme, content, rng));
         }
     }
 
-    let num_items = rand::thread_rng().gen_range(0, MAX_ROOM_ITEMS + 1);
+    let max_items = spawn::from_dungeon_level(MAX_ROOM_ITEMS, dungeon_level);
+    let num_items = rng.gen_range(0, max_items + 1);
 
     for _ in 0..num_items {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+        let (x, y) = spots[rng.gen_range(0, spots.len())];
 
         if !entity::is_blocked(x, y, map, entities) {
-            let dice = rand::random::<f32>();
-            let item = if dice < 0.7 {
-                let mut entity = Entity::new(x, y, '!', "healing potion", VIOLET, false);
-                entity.item = Some(entity::Item::Heal);
-                entity
-            } else if dice < 0.7 + 0.1 {
-                let mut object =
-                    Entity::new(x, y, '#', "scroll of lightning bolt", LIGHT_YELLOW, false);
-                object.item = Some(entity::Item::Lightning);
-                object
-            } else {
-                let mut object = Entity::new(x, y, '#', "scroll of confusion", LIGHT_YELLOW, false);
-                object.item = Some(entity::Item::Confuse);
-                object
-            };
-            entities.push(item);
+            entities.extend(make_item(x, y, dungeon_level, area.theme, content, rng));
         }
     }
 }
 
-pub fn player_move_or_attack(
-    x_amount: i32,
-    y_amount: i32,
-    game: &mut Game,
-    entities: &mut [Entity],
-) {
+/// A spawn table over the positions of `defs` in their list.
+fn spawn_table<T>(defs: &[T], weights: impl Fn(&T) -> Vec<Transition>) -> SpawnTable<usize> {
+    defs.iter()
+        .enumerate()
+        .fold(SpawnTable::new(), |table, (index, def)| {
+            table.add(index, &weights(def))
+        })
+}
+
+fn make_monster(
+    x: i32,
+    y: i32,
+    dungeon_level: u32,
+    theme: Option<RoomTheme>,
+    content: &Content,
+    rng: &mut StdRng,
+) -> Option<Entity> {
+    let index = spawn_table(&content.monsters, |monster| monster.weights(theme))
+        .pick(dungeon_level, rng)?;
+    Some(content.monsters[index].spawn(x, y))
+}
+
+fn make_item(
+    x: i32,
+    y: i32,
+    dungeon_level: u32,
+    theme: Option<RoomTheme>,
+    content: &Content,
+    rng: &mut StdRng,
+) -> Option<Entity> {
+    let index = spawn_table(&content.items, |item| item.weights(theme)).pick(dungeon_level, rng)?;
+    Some(content.items[index].spawn(x, y))
+}
+
+fn player_move_or_attack(x_amount: i32, y_amount: i32, game: &mut Game, entities: &mut [Entity]) {
+    let (x_amount, y_amount) = if game.player_confusion > 0 {
+        game.player_confusion -= 1;
+        if game.player_confusion == 0 {
+            game.messages.add("Your head clears.", WHITE);
+        }
+        let directions = [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ];
+        directions[game.rng.gen_range(0, directions.len())]
+    } else {
+        (x_amount, y_amount)
+    };
+
     let x = entities[PLAYER_ID].x + x_amount;
     let y = entities[PLAYER_ID].y + y_amount;
 
     let target_id = entities
         .iter()
         .position(|entity| entity.fighter.is_some() && entity.get_location() == (x, y));
+    let closed_door = game
+        .map
+        .get(x, y)
+        .is_some_and(|tile| tile.kind() == TileKind::ClosedDoor);
 
     match target_id {
         Some(target_id) => {
             let (player, target) = mut_two(PLAYER_ID, target_id, entities);
             player.attack(target, game);
         }
+        None if closed_door => {
+            toggle_door(x, y, game);
+            game.messages.add("You open the door.", WHITE);
+        }
         None => {
-            entity::move_by(PLAYER_ID, x_amount, y_amount, &game.map, entities);
+            let trap_id = entity::move_by(PLAYER_ID, x_amount, y_amount, &game.map, entities);
+            if let Some(trap_id) = trap_id {
+                spring_trap(trap_id, PLAYER_ID, game, entities);
+            }
+        }
+    }
+}
+
+/// Sets off a trap under the player or a monster. The player always finds
+/// out which trap it was; a monster only gives it away in plain view.
+fn spring_trap(trap_id: usize, victim_id: usize, game: &mut Game, entities: &mut [Entity]) {
+    use TrapKind::*;
+    let kind = match entities[trap_id].trap {
+        Some(trap) => trap.kind,
+        None => return,
+    };
+    let (trap_x, trap_y) = entities[trap_id].get_location();
+    let is_player = victim_id == PLAYER_ID;
+    let seen = is_player || game.fov.is_in_fov(trap_x, trap_y);
+    let victim = entities[victim_id].name.clone();
+
+    if seen {
+        if let Some(trap) = entities[trap_id].trap.as_mut() {
+            trap.hidden = false;
+        }
+        let message = if is_player {
+            format!("You step on a {}!", kind.name())
+        } else {
+            format!("The {} steps on a {}!", victim, kind.name())
+        };
+        game.messages.add(message, ORANGE);
+    }
+
+    match kind {
+        Dart => {
+            if seen {
+                game.messages.add(
+                    format!("A dart hits the {} for {} hit points.", victim, DART_DAMAGE),
+                    ORANGE,
+                );
+            }
+            entities[victim_id].take_damage(DART_DAMAGE, game);
+        }
+        Teleport => {
+            let free: Vec<_> = game
+                .map
+                .coords()
+                .filter(|&(x, y)| !entity::is_blocked(x, y, &game.map, entities))
+                .collect();
+            if !free.is_empty() {
+                let (x, y) = free[game.rng.gen_range(0, free.len())];
+                entities[victim_id].set_location(x, y);
+            }
+        }
+        Alarm => {
+            if seen {
+                game.messages.add("A loud alarm rings out!", ORANGE);
+            }
+            // Whoever sets it off, the alarm rouses the monsters within
+            // earshot of the trap and sends them after the player for a
+            // while. A monster tripping one out of sight brings its
+            // neighbours down on the player without any warning.
+            for entity in entities.iter_mut() {
+                let in_earshot = (((entity.x - trap_x).pow(2) + (entity.y - trap_y).pow(2)) as f32)
+                    .sqrt()
+                    <= ALARM_RADIUS;
+                if in_earshot && entity.ai == Some(entity::AI::Basic) {
+                    entity.ai = Some(entity::AI::Alerted {
+                        previous_ai: Box::new(entity::AI::Basic),
+                        num_turns: ALARM_NUM_TURNS,
+                    });
+                }
+            }
+        }
+        ConfusionGas => {
+            if is_player {
+                game.player_confusion = GAS_CONFUSE_NUM_TURNS;
+                game.messages
+                    .add("The gas makes your head spin!", LIGHT_GREEN);
+            } else {
+                let old_ai = entities[victim_id].ai.take().unwrap_or(entity::AI::Basic);
+                entities[victim_id].ai = Some(entity::AI::Confused {
+                    previous_ai: Box::new(old_ai),
+                    num_turns: GAS_CONFUSE_NUM_TURNS,
+                });
+            }
+        }
+    }
+}
+
+/// Looks around the player for hidden traps and secret doors and corridors,
+/// each with a fair chance of being found.
+fn search(game: &mut Game, entities: &mut [Entity]) {
+    let (player_x, player_y) = entities[PLAYER_ID].get_location();
+    let mut found = false;
+
+    for x in (player_x - SEARCH_RADIUS)..=(player_x + SEARCH_RADIUS) {
+        for y in (player_y - SEARCH_RADIUS)..=(player_y + SEARCH_RADIUS) {
+            let revealed = game.map.get(x, y).and_then(|tile| tile.kind().revealed());
+            if let Some(kind) = revealed {
+                if game.rng.gen::<f32>() < SEARCH_CHANCE {
+                    game.map.set_kind(x, y, kind);
+                    let message = if kind == TileKind::ClosedDoor {
+                        "You find a secret door!"
+                    } else {
+                        "You find a secret passage!"
+                    };
+                    game.messages.add(message, LIGHT_ORANGE);
+                    found = true;
+                }
+            }
         }
     }
+    for entity in entities.iter_mut() {
+        let near = (entity.x - player_x).abs() <= SEARCH_RADIUS
+            && (entity.y - player_y).abs() <= SEARCH_RADIUS;
+        if entity.hidden() && near && game.rng.gen::<f32>() < SEARCH_CHANCE {
+            if let Some(trap) = entity.trap.as_mut() {
+                trap.hidden = false;
+            }
+            game.messages
+                .add(format!("You find a {}!", entity.name), LIGHT_ORANGE);
+            found = true;
+        }
+    }
+    if !found {
+        game.messages.add("You search but find nothing.", WHITE);
+    }
+}
+
+/// An adjacent door of the given kind with nothing standing in it.
+fn adjacent_door(kind: TileKind, game: &Game, entities: &[Entity]) -> Option<(i32, i32)> {
+    let (player_x, player_y) = entities[PLAYER_ID].get_location();
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (player_x + dx, player_y + dy)))
+        .find(|&(x, y)| {
+            game.map.get(x, y).is_some_and(|tile| tile.kind() == kind)
+                && !entities
+                    .iter()
+                    .any(|entity| entity.get_location() == (x, y))
+        })
+}
+
+fn toggle_door(x: i32, y: i32, game: &mut Game) {
+    let toggled = match game.map.get(x, y).map(|tile| tile.kind()) {
+        Some(TileKind::ClosedDoor) => TileKind::OpenDoor,
+        Some(TileKind::OpenDoor) => TileKind::ClosedDoor,
+        _ => return,
+    };
+    game.map.set_kind(x, y, toggled);
 }
 
 fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut T, &mut T) {
@@ -426,52 +764,143 @@ fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut
     }
 }
 
-fn ai_take_turn(monster_id: usize, tcod: &Tcod, game: &mut Game, entities: &mut [Entity]) {
+fn ai_take_turn(monster_id: usize, game: &mut Game, entities: &mut [Entity]) {
     use entity::AI::*;
     if let Some(ai) = entities[monster_id].ai.take() {
         let new_ai = match ai {
-            Basic => ai_basic(monster_id, tcod, game, entities),
+            Basic => ai_basic(monster_id, game, entities),
             Confused {
                 previous_ai,
                 num_turns,
-            } => ai_confused(monster_id, tcod, game, entities, previous_ai, num_turns),
+            } => ai_confused(monster_id, game, entities, previous_ai, num_turns),
+            Alerted {
+                previous_ai,
+                num_turns,
+            } => ai_alerted(monster_id, game, entities, previous_ai, num_turns),
         };
-        entities[monster_id].ai = Some(new_ai);
+        // A trap may have killed the monster or already changed its AI.
+        let monster = &mut entities[monster_id];
+        if monster.alive && monster.ai.is_none() {
+            monster.ai = Some(new_ai);
+        }
     }
 }
 
-fn ai_basic(monster_id: usize, tcod: &Tcod, game: &mut Game, entities: &mut [Entity]) -> entity::AI {
+fn ai_basic(monster_id: usize, game: &mut Game, entities: &mut [Entity]) -> entity::AI {
+    let mut senses = match entities[monster_id].senses {
+        Some(senses) => senses,
+        None => return entity::AI::Basic,
+    };
     let (monster_x, monster_y) = entities[monster_id].get_location();
-    if tcod.fov.is_in_fov(monster_x, monster_y) {
-        if entities[monster_id].distance_to(&entities[PLAYER_ID]) >= 2.0 {
-            let (player_x, player_y) = entities[PLAYER_ID].get_location();
-            entity::move_towards(monster_id, player_x, player_y, &game.map, entities);
-        } else if entities[PLAYER_ID].fighter.map_or(false, |f| f.hp > 0) {
-            let (monster, player) = mut_two(monster_id, PLAYER_ID, entities);
-            monster.attack(player, game);
+    let player_location = entities[PLAYER_ID].get_location();
+    let noticed = entities[monster_id].distance_to(&entities[PLAYER_ID]) < 2.0
+        || (game.light.is_lit(player_location.0, player_location.1)
+            && fov::can_see(
+                &game.map,
+                (monster_x, monster_y),
+                player_location,
+                senses.sight_radius,
+            ));
+
+    if noticed && senses.last_seen.is_none() && game.fov.is_in_fov(monster_x, monster_y) {
+        let name = &entities[monster_id].name;
+        game.messages
+            .add(format!("The {} notices you.", name), LIGHT_RED);
+    }
+    senses.notice(if noticed { Some(player_location) } else { None });
+
+    if noticed {
+        chase_player(monster_id, game, entities);
+    } else if let Some(last_seen) = senses.last_seen {
+        // Go and look where the player was last seen, giving up there.
+        if (monster_x, monster_y) == last_seen {
+            senses.last_seen = None;
+        } else {
+            step_towards(monster_id, last_seen, game, entities);
         }
     }
+    entities[monster_id].senses = Some(senses);
     entity::AI::Basic
 }
 
+fn ai_alerted(
+    monster_id: usize,
+    game: &mut Game,
+    entities: &mut [Entity],
+    previous_ai: Box<entity::AI>,
+    num_turns: i32,
+) -> entity::AI {
+    if num_turns >= 0 {
+        chase_player(monster_id, game, entities);
+        entity::AI::Alerted {
+            previous_ai,
+            num_turns: num_turns - 1,
+        }
+    } else {
+        *previous_ai
+    }
+}
+
+/// Steps towards the player, opening doors on the way, or attacks once next
+/// to them.
+fn chase_player(monster_id: usize, game: &mut Game, entities: &mut [Entity]) {
+    if entities[monster_id].distance_to(&entities[PLAYER_ID]) >= 2.0 {
+        let player_location = entities[PLAYER_ID].get_location();
+        step_towards(monster_id, player_location, game, entities);
+    } else if entities[PLAYER_ID].fighter.is_some_and(|f| f.hp > 0) {
+        let (monster, player) = mut_two(monster_id, PLAYER_ID, entities);
+        monster.attack(player, game);
+    }
+}
+
+/// One step towards `(target_x, target_y)`, or opening the door in the way.
+fn step_towards(
+    monster_id: usize,
+    (target_x, target_y): (i32, i32),
+    game: &mut Game,
+    entities: &mut [Entity],
+) {
+    let (monster_x, monster_y) = entities[monster_id].get_location();
+    let (step_x, step_y) = entities[monster_id].step_towards(target_x, target_y);
+    let (next_x, next_y) = (monster_x + step_x, monster_y + step_y);
+    let door = game
+        .map
+        .get(next_x, next_y)
+        .is_some_and(|tile| tile.kind() == TileKind::ClosedDoor);
+    if door {
+        toggle_door(next_x, next_y, game);
+        game.messages.add(
+            format!("The {} opens a door.", entities[monster_id].name),
+            WHITE,
+        );
+    } else {
+        let trap_id = entity::move_by(monster_id, step_x, step_y, &game.map, entities);
+        if let Some(trap_id) = trap_id {
+            spring_trap(trap_id, monster_id, game, entities);
+        }
+    }
+}
+
 fn ai_confused(
     monster_id: usize,
-    _tcod: &Tcod,
     game: &mut Game,
     entities: &mut [Entity],
     previous_ai: Box<entity::AI>,
     num_turns: i32,
 ) -> entity::AI {
     if num_turns >= 0 {
-        entity::move_by(
+        let trap_id = entity::move_by(
             monster_id,
-            rand::thread_rng().gen_range(-1, 2),
-            rand::thread_rng().gen_range(-1, 2),
+            game.rng.gen_range(-1, 2),
+            game.rng.gen_range(-1, 2),
             &game.map,
             entities,
         );
+        if let Some(trap_id) = trap_id {
+            spring_trap(trap_id, monster_id, game, entities);
+        }
         entity::AI::Confused {
-            previous_ai: previous_ai,
+            previous_ai,
             num_turns: num_turns - 1,
         }
     } else {
@@ -483,8 +912,9 @@ fn ai_confused(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_bar(
-    panel: &mut Offscreen,
+    frontend: &mut dyn Frontend,
     x: i32,
     y: i32,
     total_width: i32,
@@ -496,30 +926,29 @@ fn render_bar(
 ) {
     let bar_width = (value as f32 / maximum as f32 * total_width as f32) as i32;
 
-    panel.set_default_background(back_color);
-    panel.rect(x, y, total_width, 1, false, BackgroundFlag::Screen);
-
-    panel.set_default_background(bar_color);
+    frontend.fill(x, y, total_width, 1, back_color);
     if bar_width > 0 {
-        panel.rect(x, y, bar_width, 1, false, BackgroundFlag::Screen);
+        frontend.fill(x, y, bar_width, 1, bar_color);
     }
 
-    panel.set_default_foreground(WHITE);
-    panel.print_ex(
-        x + total_width / 2,
-        y,
-        BackgroundFlag::None,
-        TextAlignment::Center,
-        &format!("{}: {}/{}", name, value, maximum),
-    );
+    let text = format!("{}: {}/{}", name, value, maximum);
+    frontend.print_centered(x + total_width / 2, y, &text, WHITE);
 }
 
-fn get_names_under_mouse(mouse: Mouse, entities: &[Entity], fov_map: &FovMap) -> String {
-    let (x, y) = (mouse.cx as i32, mouse.cy as i32);
+fn get_names_under_mouse(
+    mouse: Option<(i32, i32)>,
+    camera: Camera,
+    entities: &[Entity],
+    fov: &Fov,
+) -> String {
+    let (x, y) = match mouse.and_then(|(mouse_x, mouse_y)| camera.to_world(mouse_x, mouse_y)) {
+        Some(location) => location,
+        None => return String::new(),
+    };
 
     let names = entities
         .iter()
-        .filter(|obj| obj.get_location() == (x, y) && fov_map.is_in_fov(obj.x, obj.y))
+        .filter(|obj| obj.get_location() == (x, y) && fov.is_in_fov(obj.x, obj.y) && !obj.hidden())
         .map(|obj| obj.name.clone())
         .collect::<Vec<_>>();
 
@@ -543,89 +972,54 @@ fn pick_item_up(object_id: usize, game: &mut Game, entities: &mut Vec<Entity>) {
     }
 }
 
-fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32, root: &mut Root) -> Option<usize> {
-    assert!(
-        options.len() <= 26,
-        "Cannot have a menu with more than 26 options."
-    );
-
-    let header_height = root.get_height_rect(0, 0, width, WINDOW_HEIGHT, header);
-    let height = options.len() as i32 + header_height;
-
-    let mut window = Offscreen::new(width, height);
-
-    window.set_default_foreground(WHITE);
-    window.print_rect_ex(
-        0,
-        0,
-        width,
-        height,
-        BackgroundFlag::None,
-        TextAlignment::Left,
-        header,
-    );
-
-    for (index, option_text) in options.iter().enumerate() {
-        let menu_letter = (b'a' + index as u8) as char;
-        let text = format!("({}) {}", menu_letter, option_text.as_ref());
-        window.print_ex(
-            0,
-            header_height + index as i32,
-            BackgroundFlag::None,
-            TextAlignment::Left,
-            text,
-        );
-    }
-
-    let x = WINDOW_WIDTH / 2 - width / 2;
-    let y = WINDOW_HEIGHT / 2 - height / 2;
-    blit(&window, (0, 0), (width, height), root, (x, y), 1.0, 0.7);
-
-    root.flush();
-    let key = root.wait_for_keypress(true);
-
-    if key.printable.is_alphabetic() {
-        let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
-        if index < options.len() {
-            Some(index)
-        } else {
-            None
-        }
-    } else {
-        None
-    }
-}
-
-fn inventory_menu(inventory: &[Entity], header: &str, root: &mut Root) -> Option<usize> {
-    let options = if inventory.len() == 0 {
+fn inventory_menu(
+    inventory: &[Entity],
+    header: &str,
+    frontend: &mut dyn Frontend,
+) -> Option<usize> {
+    let options = if inventory.is_empty() {
         vec!["Inventory is empty.".into()]
     } else {
-        inventory.iter().map(|item| item.name.clone()).collect()
+        inventory
+            .iter()
+            .map(|item| {
+                if item.equipped {
+                    format!("{} (equipped)", item.name)
+                } else {
+                    item.name.clone()
+                }
+            })
+            .collect()
     };
 
-    let inventory_index = menu(header, &options, INVENTORY_WIDTH, root);
+    let inventory_index = frontend.menu(header, &options, INVENTORY_WIDTH);
 
-    if inventory.len() > 0 {
+    if !inventory.is_empty() {
         inventory_index
     } else {
         None
     }
 }
 
-fn use_item(inventory_id: usize, tcod: &mut Tcod, game: &mut Game, entities: &mut [Entity]) {
+/// Returns whether using the item took the player's turn.
+fn use_item(inventory_id: usize, game: &mut Game, entities: &mut [Entity]) -> bool {
     use entity::Item::*;
     if let Some(item) = game.inventory[inventory_id].item {
-        let on_use = match item {
-            Heal => cast_heal,
-            Lightning => cast_lightning,
-            Confuse => cast_confuse,
+        let result = match item {
+            Heal { amount } => cast_heal(amount, game, entities),
+            Lightning { damage, range } => cast_lightning(damage, range, game, entities),
+            Confuse { range, turns } => cast_confuse(range, turns, game, entities),
+            Gear { slot, .. } => toggle_gear(inventory_id, slot, game, entities),
         };
-        match on_use(inventory_id, tcod, game, entities) {
+        match result {
             entity::UseResult::UsedUp => {
                 game.inventory.remove(inventory_id);
+                true
             }
+            entity::UseResult::Kept => true,
             entity::UseResult::Cancelled => {
                 game.messages.add("Cancelled", WHITE);
+                false
             }
         }
     } else {
@@ -633,15 +1027,11 @@ fn use_item(inventory_id: usize, tcod: &mut Tcod, game: &mut Game, entities: &mu
             format!("The {} cannot be used.", game.inventory[inventory_id].name),
             WHITE,
         );
+        false
     }
 }
 
-fn cast_heal(
-    _inventory_id: usize,
-    _tcod: &mut Tcod,
-    game: &mut Game,
-    entities: &mut [Entity],
-) -> entity::UseResult {
+fn cast_heal(amount: i32, game: &mut Game, entities: &mut [Entity]) -> entity::UseResult {
     if let Some(fighter) = entities[PLAYER_ID].fighter {
         if fighter.hp == fighter.max_hp {
             game.messages.add("You are already at full health.", RED);
@@ -649,38 +1039,86 @@ fn cast_heal(
         }
         game.messages
             .add("Your wounds start to feel better!", LIGHT_VIOLET);
-        entities[PLAYER_ID].heal(HEAL_AMOUNT);
+        entities[PLAYER_ID].heal(amount);
         return entity::UseResult::UsedUp;
     }
     entity::UseResult::Cancelled
 }
 
+/// Takes the gear off if it is worn. Otherwise puts it on, taking off
+/// whatever was in its slot first.
+fn toggle_gear(
+    inventory_id: usize,
+    slot: entity::Slot,
+    game: &mut Game,
+    entities: &mut [Entity],
+) -> entity::UseResult {
+    if game.inventory[inventory_id].equipped {
+        set_equipped(inventory_id, false, game, entities);
+        return entity::UseResult::Kept;
+    }
+    let worn = game.inventory.iter().position(|item| {
+        item.equipped
+            && matches!(item.item, Some(entity::Item::Gear { slot: worn, .. }) if worn == slot)
+    });
+    if let Some(worn) = worn {
+        set_equipped(worn, false, game, entities);
+    }
+    set_equipped(inventory_id, true, game, entities);
+    entity::UseResult::Kept
+}
+
+/// Puts on or takes off gear, adding its bonus to the player or taking it
+/// away again.
+fn set_equipped(inventory_id: usize, equipped: bool, game: &mut Game, entities: &mut [Entity]) {
+    let gear = &mut game.inventory[inventory_id];
+    let (power, defense) = match gear.item {
+        Some(entity::Item::Gear { power, defense, .. }) => (power, defense),
+        _ => return,
+    };
+    if gear.equipped == equipped {
+        return;
+    }
+    gear.equipped = equipped;
+    let sign = if equipped { 1 } else { -1 };
+    if let Some(fighter) = entities[PLAYER_ID].fighter.as_mut() {
+        fighter.power += sign * power;
+        fighter.defense += sign * defense;
+    }
+    let message = if equipped {
+        format!("You equip the {}.", gear.name)
+    } else {
+        format!("You take off the {}.", gear.name)
+    };
+    game.messages.add(message, LIGHT_GREEN);
+}
+
 fn cast_lightning(
-    _inventory_id: usize,
-    tcod: &mut Tcod,
+    damage: i32,
+    range: i32,
     game: &mut Game,
     entities: &mut [Entity],
 ) -> entity::UseResult {
-    let monster_id = closest_monster(tcod, entities, LIGHTNING_RANGE);
+    let monster_id = closest_monster(game, entities, range);
     if let Some(monster_id) = monster_id {
         game.messages.add(
             format!(
                 "A lightning bolt strikes the {} with a loud thunder! \
                  The damage is {} hit points.",
-                 entities[monster_id].name, LIGHTNING_DAMAGE
+                entities[monster_id].name, damage
             ),
             LIGHT_BLUE,
         );
-        entities[monster_id].take_damage(LIGHTNING_DAMAGE, game);
+        entities[monster_id].take_damage(damage, game);
         entity::UseResult::UsedUp
     } else {
         game.messages
             .add("No enemy is close enough to strike.", RED);
-            entity::UseResult::Cancelled
+        entity::UseResult::Cancelled
     }
 }
 
-fn closest_monster(tcod: &Tcod, entities: &[Entity], max_range: i32) -> Option<usize> {
+fn closest_monster(game: &Game, entities: &[Entity], max_range: i32) -> Option<usize> {
     let mut closest_enemy = None;
     let mut closest_dist = (max_range + 1) as f32;
 
@@ -688,7 +1126,7 @@ fn closest_monster(tcod: &Tcod, entities: &[Entity], max_range: i32) -> Option<u
         if (id != PLAYER_ID)
             && object.fighter.is_some()
             && object.ai.is_some()
-            && tcod.fov.is_in_fov(object.x, object.y)
+            && game.fov.is_in_fov(object.x, object.y)
         {
             let dist = entities[PLAYER_ID].distance_to(object);
             if dist < closest_dist {
@@ -701,17 +1139,17 @@ fn closest_monster(tcod: &Tcod, entities: &[Entity], max_range: i32) -> Option<u
 }
 
 fn cast_confuse(
-    _inventory_id: usize,
-    tcod: &mut Tcod,
+    range: i32,
+    turns: i32,
     game: &mut Game,
     entities: &mut [Entity],
 ) -> entity::UseResult {
-    let monster_id = closest_monster(tcod, entities, CONFUSE_RANGE);
+    let monster_id = closest_monster(game, entities, range);
     if let Some(monster_id) = monster_id {
         let old_ai = entities[monster_id].ai.take().unwrap_or(entity::AI::Basic);
         entities[monster_id].ai = Some(entity::AI::Confused {
             previous_ai: Box::new(old_ai),
-            num_turns: CONFUSE_NUM_TURNS,
+            num_turns: turns,
         });
         game.messages.add(
             format!(
@@ -724,28 +1162,116 @@ fn cast_confuse(
     } else {
         game.messages
             .add("No enemy is close enough to strike.", RED);
-            entity::UseResult::Cancelled
+        entity::UseResult::Cancelled
     }
 }
 
-fn main() {
-    let root = Root::initializer()
-        .font("res/arial10x10.png", FontLayout::Tcod)
-        .font_type(FontType::Greyscale)
-        .size(WINDOW_WIDTH, WINDOW_HEIGHT)
-        .title("Rust roguelike demo")
-        .init();
-
-    let mut tcod = Tcod {
-        root,
-        console: Offscreen::new(MAP_WIDTH, MAP_HEIGHT),
-        panel: Offscreen::new(WINDOW_WIDTH, PANEL_HEIGHT),
-        fov: FovMap::new(MAP_WIDTH, MAP_HEIGHT),
-        key: Default::default(),
-        mouse: Default::default(),
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
+
+fn seed_from_args() -> usize {
+    let seed = match arg_value("--seed") {
+        Some(seed) => seed,
+        None => return rand::random(),
     };
+    seed.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid seed '{}', expected a whole number", seed);
+        process::exit(1);
+    })
+}
+
+fn generator_from_args() -> Box<dyn MapGenerator> {
+    let name = arg_value("--layout").unwrap_or_else(|| "rooms".into());
+    mapgen::by_name(&name).unwrap_or_else(|| {
+        eprintln!(
+            "Unknown layout '{}', expected one of: {}",
+            name,
+            mapgen::names().join(", ")
+        );
+        process::exit(1);
+    })
+}
+
+/// `--frontend terminal` plays in the terminal instead of a window, for
+/// machines that can't open one.
+fn frontend_from_args() -> Box<dyn Frontend> {
+    let name = arg_value("--frontend").unwrap_or_else(|| FRONTENDS[0].into());
+    match name.as_str() {
+        #[cfg(feature = "tcod")]
+        "tcod" => Box::new(TcodFrontend::new(
+            WINDOW_WIDTH,
+            WINDOW_HEIGHT,
+            "Rust roguelike demo",
+            LIMIT_FPS,
+        )),
+        "terminal" => match TerminalFrontend::new(LIMIT_FPS) {
+            Ok(frontend) => Box::new(frontend),
+            Err(error) => {
+                eprintln!("Cannot play in this terminal: {}", error);
+                process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!(
+                "Unknown frontend '{}', expected one of: {}",
+                name,
+                FRONTENDS.join(", ")
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// `--size WIDTHxHEIGHT` fixes the size of every level instead of letting
+/// the layout choose.
+fn map_size_from_args() -> Option<(i32, i32)> {
+    let size = arg_value("--size")?;
+    let parsed = size
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+    match parsed {
+        Some((width, height))
+            if (mapgen::MIN_MAP_WIDTH..=mapgen::MAX_MAP_WIDTH).contains(&width)
+                && (mapgen::MIN_MAP_HEIGHT..=mapgen::MAX_MAP_HEIGHT).contains(&height) =>
+        {
+            Some((width, height))
+        }
+        _ => {
+            eprintln!(
+                "Invalid map size '{}', expected WIDTHxHEIGHT between {}x{} and {}x{}",
+                size,
+                mapgen::MIN_MAP_WIDTH,
+                mapgen::MIN_MAP_HEIGHT,
+                mapgen::MAX_MAP_WIDTH,
+                mapgen::MAX_MAP_HEIGHT
+            );
+            process::exit(1);
+        }
+    }
+}
 
-    tcod::system::set_fps(LIMIT_FPS);
+fn load_content() -> Result<Content, String> {
+    Ok(Content {
+        prefabs: Prefab::load_all(Path::new(PREFAB_DIR))?,
+        monsters: monsters::load(Path::new(MONSTERS_FILE))?,
+        items: items::load(Path::new(ITEMS_FILE))?,
+    })
+}
+
+/// A new game on the first level of the dungeon, with the player standing
+/// at its start.
+fn new_game(
+    seed: usize,
+    generator: Box<dyn MapGenerator>,
+    map_size: Option<(i32, i32)>,
+    content: Content,
+) -> (Game, Vec<Entity>) {
+    let mut rng = StdRng::from_seed(&[seed][..]);
 
     let default_x = 0;
     let default_y = 0;
@@ -759,60 +1285,306 @@ fn main() {
         on_death: entity::DeathCallback::Player,
     });
 
+    player.light = Some(PLAYER_TORCH);
     player.alive = true;
 
-    let mut previous_player_location = player.get_location();
-
     let mut entities = vec![player];
 
     let mut game = Game {
-        map: make_map(&mut entities),
+        map: make_level(
+            generator.as_ref(),
+            &content,
+            map_size.unwrap_or_else(|| generator.map_size(1)),
+            1,
+            &mut entities,
+            &mut rng,
+        ),
         messages: Messages::new(),
         inventory: vec![],
+        seed,
+        rng,
+        dungeon_level: 1,
+        fov: Fov::new(0, 0),
+        light: LightMap::dark(0, 0),
+        levels: HashMap::new(),
+        player_confusion: 0,
+        map_size,
+        generator,
+        content,
     };
 
-    for y in 0..MAP_HEIGHT {
-        for x in 0..MAP_WIDTH {
-            tcod.fov.set(
-                x,
-                y,
-                !game.map[x as usize][y as usize].block_sight,
-                !game.map[x as usize][y as usize].blocked,
-            );
-        }
-    }
+    update_fov(&mut game, &entities);
 
     game.messages.add(
         "Welcome stranger! Prepare to perish in the Tombs of the Ancient Kings.",
         RED,
     );
+    game.messages
+        .add(format!("Dungeon seed: {}", game.seed), LIGHT_GREY);
+
+    (game, entities)
+}
 
-    while !tcod.root.window_closed() {
+fn main() {
+    let seed = seed_from_args();
+    let generator = generator_from_args();
+    let map_size = map_size_from_args();
+    let content = load_content().unwrap_or_else(|error| {
+        eprintln!("Failed to load game data: {}", error);
+        process::exit(1);
+    });
+    let (mut game, mut entities) = new_game(seed, generator, map_size, content);
+
+    let mut frontend = frontend_from_args();
+    play(frontend.as_mut(), &mut game, &mut entities);
+}
+
+/// Runs the game until the player quits or closes the window.
+fn play(frontend: &mut dyn Frontend, game: &mut Game, entities: &mut Vec<Entity>) {
+    let mut ui = Ui {
+        camera: Camera::new(0, 0),
+        mouse: None,
+    };
+    let mut fov_recompute = true;
+
+    while !frontend.is_closed() {
         let player_location = entities[PLAYER_ID].get_location();
-        let fov_recompute = previous_player_location != player_location;
+        let lights = light_sources(entities);
 
-        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
-            Some((_, Event::Mouse(m))) => tcod.mouse = m,
-            Some((_, Event::Key(k))) => tcod.key = k,
-            _ => tcod.key = Default::default(),
-        }
+        let key = match frontend.poll_input() {
+            Some(Input::Key(key)) => Some(key),
+            Some(Input::Mouse(x, y)) => {
+                ui.mouse = Some((x, y));
+                None
+            }
+            None => None,
+        };
 
-        tcod.console.clear();
-        render_all(&mut tcod, &mut game, &entities, fov_recompute);
-        tcod.root.flush();
+        frontend.clear();
+        render_all(frontend, &mut ui, game, entities, fov_recompute);
+        frontend.present();
 
-        previous_player_location = player_location;
-        let player_action = handle_key_input(&mut tcod, &mut entities, &mut game);
+        let player_action = match key {
+            Some(key) => handle_key_input(key, frontend, entities, game),
+            None => PlayerAction::DidntTakeTurn,
+        };
         if player_action == PlayerAction::Exit {
             break;
         }
 
         if entities[PLAYER_ID].alive && player_action != PlayerAction::DidntTakeTurn {
-            for id in 0..entities.len() {
-                if entities[id].ai.is_some() {
-                    ai_take_turn(id, &tcod, &mut game, &mut entities);
+            let (player_x, player_y) = entities[PLAYER_ID].get_location();
+            let turns = if (player_x, player_y) != player_location {
+                game.map[(player_x, player_y)]
+                    .kind()
+                    .terrain()
+                    .movement_cost
+            } else {
+                1
+            };
+            for _ in 0..turns {
+                for id in 0..entities.len() {
+                    if entities[id].ai.is_some() {
+                        ai_take_turn(id, game, entities);
+                    }
                 }
             }
         }
+
+        // What the player sees only changes with where they stand, the
+        // lights and the map. Doors opening and other terrain changes are
+        // picked up from the map itself.
+        fov_recompute = entities[PLAYER_ID].get_location() != player_location
+            || light_sources(entities) != lights;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use headless::Headless;
+    use map::Tile;
+
+    fn test_game(seed: usize) -> (Game, Vec<Entity>) {
+        let content = load_content().unwrap();
+        new_game(seed, mapgen::by_name("rooms").unwrap(), None, content)
+    }
+
+    #[test]
+    fn play_runs_turns_without_a_window() {
+        let (mut game, mut entities) = test_game(1);
+        let (start_x, start_y) = entities[PLAYER_ID].get_location();
+        let steps = [
+            (Key::Up, (0, -1)),
+            (Key::Down, (0, 1)),
+            (Key::Left, (-1, 0)),
+            (Key::Right, (1, 0)),
+        ];
+        let (key, (dx, dy)) = steps
+            .iter()
+            .copied()
+            .find(|&(_, (dx, dy))| {
+                !entity::is_blocked(start_x + dx, start_y + dy, &game.map, &entities)
+            })
+            .unwrap();
+
+        let keys = vec![key, Key::Char('i'), Key::Char('z'), Key::Char('s')];
+        let mut frontend = Headless::new(80, 50, keys);
+        play(&mut frontend, &mut game, &mut entities);
+
+        assert_eq!(
+            entities[PLAYER_ID].get_location(),
+            (start_x + dx, start_y + dy)
+        );
+        assert_eq!(frontend.frames.len(), 4);
+        assert!(frontend.frames[0].contains('@'));
+        assert!(frontend.frames[0].contains("Dungeon level: 1"));
+        assert!(frontend.frames[2].contains("Inventory is empty."));
+        assert!(frontend.row(44).contains("HP: 30/30"));
+    }
+
+    #[test]
+    fn opening_a_door_refreshes_sight_by_itself() {
+        let (mut game, mut entities) = test_game(1);
+        let mut map = Map::new(20, 3, Tile::wall());
+        for x in 1..19 {
+            map.set_kind(x, 1, TileKind::Floor);
+        }
+        map.set_kind(5, 1, TileKind::ClosedDoor);
+        game.map = map;
+        entities.truncate(PLAYER_ID + 1);
+        entities[PLAYER_ID].set_location(4, 1);
+        update_fov(&mut game, &entities);
+        assert!(!game.fov.is_in_fov(8, 1));
+
+        // Opening the door takes a turn without the player moving, and the
+        // unknown key after it only redraws.
+        let mut frontend = Headless::new(80, 50, vec![Key::Char('o'), Key::Char('z')]);
+        play(&mut frontend, &mut game, &mut entities);
+
+        assert_eq!(game.map[(5, 1)].kind(), TileKind::OpenDoor);
+        assert!(game.fov.is_in_fov(8, 1));
+    }
+
+    #[test]
+    fn monsters_spot_a_player_only_within_their_sight_radius() {
+        let (mut game, mut entities) = test_game(1);
+        let mut map = Map::new(20, 3, Tile::wall());
+        for x in 1..19 {
+            map.set_kind(x, 1, TileKind::Floor);
+        }
+        game.map = map;
+        entities.truncate(PLAYER_ID + 1);
+        entities[PLAYER_ID].set_location(2, 1);
+        for (x, sight_radius) in [(8, 4), (10, 8)] {
+            let mut monster = Entity::new(x, 1, 'o', "orc", WHITE, true);
+            monster.alive = true;
+            monster.ai = Some(entity::AI::Basic);
+            monster.senses = Some(entity::Senses::new(sight_radius, 0));
+            entities.push(monster);
+        }
+        update_fov(&mut game, &entities);
+
+        // Both have a clear line to the torch-lit player, but only the second
+        // sees that far.
+        ai_take_turn(1, &mut game, &mut entities);
+        ai_take_turn(2, &mut game, &mut entities);
+        assert_eq!(entities[1].senses.unwrap().last_seen, None);
+        assert_eq!(entities[2].senses.unwrap().last_seen, Some((2, 1)));
+    }
+
+    #[test]
+    fn arriving_on_occupied_stairs_puts_the_player_beside_them() {
+        let (mut game, mut entities) = test_game(1);
+        change_level(2, &mut game, &mut entities);
+
+        let level_1 = game.levels.get_mut(&1).unwrap();
+        let (stairs_x, stairs_y) = level_1
+            .entities
+            .iter()
+            .find(|entity| entity.stairs == Some(Stairs::Down))
+            .unwrap()
+            .get_location();
+        let mut monster = Entity::new(stairs_x, stairs_y, 'o', "orc", WHITE, true);
+        monster.alive = true;
+        level_1.entities.push(monster);
+
+        change_level(1, &mut game, &mut entities);
+        let (x, y) = entities[PLAYER_ID].get_location();
+        assert_ne!((x, y), (stairs_x, stairs_y));
+        assert!((x - stairs_x).abs() <= 1 && (y - stairs_y).abs() <= 1);
+        assert!(!entity::is_blocked(
+            x,
+            y,
+            &game.map,
+            &entities[PLAYER_ID + 1..]
+        ));
+    }
+
+    #[test]
+    fn equipping_a_second_weapon_replaces_the_first() {
+        let (mut game, mut entities) = test_game(1);
+        let gear = |name: &str| {
+            let def = game.content.items.iter().find(|item| item.name == name);
+            def.unwrap().spawn(0, 0)
+        };
+        let inventory = vec![
+            gear("short sword"),
+            gear("short sword"),
+            gear("leather armour"),
+        ];
+        game.inventory = inventory;
+        let base = entities[PLAYER_ID].fighter.unwrap();
+        let bonus = |entities: &[Entity]| {
+            let fighter = entities[PLAYER_ID].fighter.unwrap();
+            (fighter.power - base.power, fighter.defense - base.defense)
+        };
+
+        assert!(use_item(0, &mut game, &mut entities));
+        assert_eq!(bonus(&entities), (2, 0));
+        assert!(use_item(1, &mut game, &mut entities));
+        assert_eq!(bonus(&entities), (2, 0));
+        assert!(!game.inventory[0].equipped && game.inventory[1].equipped);
+        assert!(use_item(2, &mut game, &mut entities));
+        assert_eq!(bonus(&entities), (2, 1));
+
+        // Using worn gear again takes it off.
+        assert!(use_item(1, &mut game, &mut entities));
+        assert_eq!(bonus(&entities), (0, 1));
+        assert_eq!(game.inventory.len(), 3);
+    }
+
+    #[test]
+    fn themed_rooms_spawn_more_of_what_they_favour() {
+        let content = load_content().unwrap();
+        let map = Map::new(12, 12, Tile::empty());
+        let spawn_into = |theme: Option<RoomTheme>, depth: u32| {
+            let area = SpawnArea {
+                tiles: map.coords().collect(),
+                room: None,
+                theme,
+            };
+            let mut spawned = vec![];
+            for seed in 0..300 {
+                let mut rng = StdRng::from_seed(&[seed][..]);
+                let mut entities = vec![Entity::new(0, 0, '@', "player", WHITE, true)];
+                place_entities(&area, depth, &content, &map, &mut entities, &mut rng);
+                spawned.extend(entities.drain(PLAYER_ID + 1..));
+            }
+            spawned
+        };
+        let count = |spawned: &[Entity], wanted: &dyn Fn(&Entity) -> bool| {
+            spawned.iter().filter(|entity| wanted(entity)).count()
+        };
+
+        let is_gear = |entity: &Entity| matches!(entity.item, Some(entity::Item::Gear { .. }));
+        let armory = spawn_into(Some(RoomTheme::Armory), 1);
+        let plain = spawn_into(None, 1);
+        assert!(count(&armory, &is_gear) > 2 * count(&plain, &is_gear));
+
+        let is_leech = |entity: &Entity| entity.name == "giant leech";
+        let flooded = spawn_into(Some(RoomTheme::Flooded), 3);
+        let plain = spawn_into(None, 3);
+        assert!(count(&flooded, &is_leech) > 2 * count(&plain, &is_leech));
     }
 }
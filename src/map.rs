@@ -0,0 +1,240 @@
+use std::ops::Index;
+
+use crate::tile::{TileKind, TileMemory};
+
+/// The tiles of one level, stored row by row in a single `Vec`.
+///
+/// `get` returns `None` off the edge of the map. Indexing with `map[(x, y)]`
+/// is for coordinates already known to be inside, and panics otherwise.
+///
+/// Tiles can only be read. Terrain and lighting change through `set_kind`
+/// and `set_lit`, while generating a level and in play alike, and those note
+/// when what can be seen or walked through has changed so anything worked
+/// out from the map knows to start again. Only the player's memory of a tile
+/// can be changed directly.
+#[derive(Clone, Debug)]
+pub struct Map {
+    width: i32,
+    height: i32,
+    tiles: Vec<Tile>,
+    changed: bool,
+}
+
+impl Map {
+    pub fn new(width: i32, height: i32, tile: Tile) -> Self {
+        Map {
+            width,
+            height,
+            tiles: vec![tile; (width * height) as usize],
+            changed: false,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<&Tile> {
+        if self.in_bounds(x, y) {
+            Some(&self.tiles[self.index_of(x, y)])
+        } else {
+            None
+        }
+    }
+
+    /// What the player remembers of the tile at `(x, y)`, if it is on the
+    /// map.
+    pub fn memory_mut(&mut self, x: i32, y: i32) -> Option<&mut TileMemory> {
+        if self.in_bounds(x, y) {
+            let index = self.index_of(x, y);
+            Some(&mut self.tiles[index].memory)
+        } else {
+            None
+        }
+    }
+
+    /// Turns the tile at `(x, y)` into `kind`, keeping everything else about
+    /// it. Does nothing off the edge of the map.
+    pub fn set_kind(&mut self, x: i32, y: i32, kind: TileKind) {
+        if !self.in_bounds(x, y) {
+            return;
+        }
+        let index = self.index_of(x, y);
+        let tile = &mut self.tiles[index];
+        let (old, new) = (tile.kind.terrain(), kind.terrain());
+        tile.kind = kind;
+        if old.blocks_sight != new.blocks_sight || old.passable != new.passable {
+            self.changed = true;
+        }
+    }
+
+    /// Makes the tile at `(x, y)` part of a room lit all over, or not. Does
+    /// nothing off the edge of the map.
+    pub fn set_lit(&mut self, x: i32, y: i32, lit: bool) {
+        if !self.in_bounds(x, y) {
+            return;
+        }
+        let index = self.index_of(x, y);
+        let tile = &mut self.tiles[index];
+        if tile.lit != lit {
+            tile.lit = lit;
+            self.changed = true;
+        }
+    }
+
+    /// Whether `set_kind` or `set_lit` has changed sight lines, light or
+    /// walkability since the last call.
+    pub fn take_changes(&mut self) -> bool {
+        std::mem::replace(&mut self.changed, false)
+    }
+
+    /// Every coordinate on the map, row by row.
+    pub fn coords(&self) -> impl Iterator<Item = (i32, i32)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), &Tile)> {
+        self.coords().zip(self.tiles.iter())
+    }
+
+    /// Every tile's kind with what the player remembers of it.
+    pub fn iter_memory_mut(
+        &mut self,
+    ) -> impl Iterator<Item = ((i32, i32), TileKind, &mut TileMemory)> {
+        self.coords()
+            .zip(self.tiles.iter_mut())
+            .map(|(coords, tile)| (coords, tile.kind(), &mut tile.memory))
+    }
+
+    fn index_of(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+}
+
+/// One tile of a map. Its kind and lighting are private to this module and
+/// `Map` never hands out a `&mut Tile`, so they only change through the map,
+/// which notices when they do.
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    kind: TileKind,
+    /// Part of a room lit all over.
+    lit: bool,
+    pub memory: TileMemory,
+}
+
+impl Tile {
+    pub fn new(kind: TileKind) -> Self {
+        Tile {
+            kind,
+            lit: false,
+            memory: TileMemory::default(),
+        }
+    }
+
+    pub fn kind(&self) -> TileKind {
+        self.kind
+    }
+
+    pub fn lit(&self) -> bool {
+        self.lit
+    }
+
+    pub fn empty() -> Self {
+        Tile::new(TileKind::Floor)
+    }
+
+    pub fn wall() -> Self {
+        Tile::new(TileKind::Wall)
+    }
+
+    pub fn blocked(&self) -> bool {
+        !self.kind.terrain().passable
+    }
+
+    pub fn block_sight(&self) -> bool {
+        self.kind.terrain().blocks_sight
+    }
+
+    /// Whether something could get through, opening a door or finding a
+    /// secret if it has to.
+    pub fn traversable(&self) -> bool {
+        !self.blocked() || self.kind == TileKind::ClosedDoor || self.kind.revealed().is_some()
+    }
+}
+
+impl Index<(i32, i32)> for Map {
+    type Output = Tile;
+
+    fn index(&self, (x, y): (i32, i32)) -> &Tile {
+        assert!(
+            self.in_bounds(x, y),
+            "({}, {}) is outside the {}x{} map",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        &self.tiles[self.index_of(x, y)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_is_none_off_the_edge() {
+        let map = Map::new(4, 3, Tile::wall());
+        assert!(map.get(0, 0).is_some());
+        assert!(map.get(3, 2).is_some());
+        assert!(map.get(-1, 0).is_none());
+        assert!(map.get(0, -1).is_none());
+        assert!(map.get(4, 0).is_none());
+        assert!(map.get(0, 3).is_none());
+    }
+
+    #[test]
+    fn set_kind_notes_changes_to_sight_and_walking() {
+        let mut map = Map::new(4, 3, Tile::empty());
+        map.memory_mut(1, 1).unwrap().explored = true;
+
+        map.set_kind(1, 1, TileKind::Grass);
+        assert!(!map.take_changes());
+        assert!(map[(1, 1)].memory.explored);
+
+        map.set_kind(1, 1, TileKind::ClosedDoor);
+        assert!(map.take_changes());
+        assert!(!map.take_changes());
+
+        map.set_kind(9, 9, TileKind::Wall);
+        assert!(!map.take_changes());
+
+        map.set_lit(2, 1, true);
+        assert!(map.take_changes());
+        map.set_lit(2, 1, true);
+        assert!(!map.take_changes());
+    }
+
+    #[test]
+    fn coords_match_tiles() {
+        let mut map = Map::new(4, 3, Tile::wall());
+        map.set_kind(2, 1, TileKind::Floor);
+
+        let open: Vec<_> = map
+            .iter()
+            .filter(|(_, tile)| !tile.blocked())
+            .map(|(coords, _)| coords)
+            .collect();
+        assert_eq!(open, vec![(2, 1)]);
+        assert_eq!(map.coords().count(), 12);
+    }
+}
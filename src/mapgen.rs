@@ -0,0 +1,871 @@
+use rand::{Rng, StdRng};
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+
+use crate::map::{Map, Tile};
+use crate::prefab::{Prefab, PrefabTile};
+use crate::rect::Rect;
+use crate::themes::{self, Feature, RoomTheme};
+use crate::tile::TileKind;
+
+/// The size of a level when the generator has no reason to pick another.
+pub const DEFAULT_MAP_WIDTH: i32 = 80;
+pub const DEFAULT_MAP_HEIGHT: i32 = 43;
+
+/// The smallest level every generator can still fill.
+pub const MIN_MAP_WIDTH: i32 = 40;
+pub const MIN_MAP_HEIGHT: i32 = 30;
+
+/// The largest level `--size` may ask for, well short of what would take
+/// too long to generate or light.
+pub const MAX_MAP_WIDTH: i32 = 400;
+pub const MAX_MAP_HEIGHT: i32 = 400;
+
+const ROOM_MAX_SIZE: i32 = 10;
+const ROOM_MIN_SIZE: i32 = 6;
+const MAX_ROOMS: i32 = 30;
+
+const BSP_MAX_DEPTH: i32 = 5;
+const BSP_MIN_LEAF_SIZE: i32 = ROOM_MIN_SIZE + 2;
+
+const CAVE_WALL_CHANCE: f32 = 0.45;
+const CAVE_SMOOTHING_PASSES: i32 = 5;
+const CAVE_MIN_FLOOR: usize = 400;
+const CAVE_MAX_ATTEMPTS: i32 = 20;
+const CAVE_AREA_SIZE: i32 = 10;
+const CAVE_MIN_AREA_TILES: usize = 30;
+const CAVE_MAX_MAP_WIDTH: i32 = 160;
+const CAVE_MAX_MAP_HEIGHT: i32 = 90;
+
+const ROOM_THEME_CHANCE: f32 = 0.3;
+
+const SECRET_DOOR_CHANCE: f32 = 0.1;
+const MAX_SECRET_CORRIDORS: usize = 2;
+
+const MAX_PREFABS_PER_LEVEL: i32 = 2;
+
+const LIT_ROOM_CHANCE: f32 = 0.35;
+const MAX_WALL_TORCHES: usize = 4;
+const PREFAB_PLACEMENT_ATTEMPTS: i32 = 50;
+
+/// Everything a generator decides about a level. Spawning monsters and items
+/// into `spawn_areas` and onto the prefab spots, and putting the features in
+/// place, is left to the caller.
+pub struct GeneratedLevel {
+    pub map: Map,
+    pub player_start: (i32, i32),
+    pub stairs: (i32, i32),
+    pub rooms: Vec<Rect>,
+    pub spawn_areas: Vec<SpawnArea>,
+    pub monster_spots: Vec<(i32, i32)>,
+    pub item_spots: Vec<(i32, i32)>,
+    pub features: Vec<((i32, i32), Feature)>,
+}
+
+/// Tiles monsters and items are spread over together: a room, or a patch of
+/// cave.
+pub struct SpawnArea {
+    pub tiles: Vec<(i32, i32)>,
+    pub room: Option<Rect>,
+    pub theme: Option<RoomTheme>,
+}
+
+pub trait MapGenerator {
+    fn name(&self) -> &'static str;
+
+    /// How big a map to generate for the given depth.
+    fn map_size(&self, _dungeon_level: u32) -> (i32, i32) {
+        (DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT)
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut StdRng) -> GeneratedLevel;
+}
+
+pub fn generators() -> Vec<Box<dyn MapGenerator>> {
+    vec![
+        Box::new(RoomsGenerator),
+        Box::new(BspGenerator),
+        Box::new(CaveGenerator),
+    ]
+}
+
+pub fn by_name(name: &str) -> Option<Box<dyn MapGenerator>> {
+    generators()
+        .into_iter()
+        .find(|generator| generator.name() == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    generators()
+        .iter()
+        .map(|generator| generator.name())
+        .collect()
+}
+
+/// Runs `generator`, sets some of `prefabs` into the solid rock it left and
+/// then tunnels any floor the player could not walk to back into the rest of
+/// the level.
+pub fn build_level(
+    generator: &dyn MapGenerator,
+    prefabs: &[Prefab],
+    width: i32,
+    height: i32,
+    rng: &mut StdRng,
+) -> GeneratedLevel {
+    let mut level = generator.generate(width, height, rng);
+    if !prefabs.is_empty() {
+        for _ in 0..MAX_PREFABS_PER_LEVEL {
+            let mut prefab = prefabs[rng.gen_range(0, prefabs.len())].clone();
+            for _ in 0..rng.gen_range(0, 4) {
+                prefab = prefab.rotated();
+            }
+            if rng.gen() {
+                prefab = prefab.mirrored();
+            }
+            place_prefab(&prefab, &mut level, rng);
+        }
+    }
+    connect_unreachable(&mut level.map, level.player_start, rng);
+    for room in &level.rooms {
+        place_doors(*room, &mut level.map);
+    }
+    theme_rooms(&mut level, rng);
+    hide_secrets(&mut level, rng);
+    light_level(&mut level, rng);
+    level
+}
+
+/// Lights some rooms all over, walls included, and hangs a few torches on
+/// walls beside the floor. Every other tile is left dark.
+pub fn light_level(level: &mut GeneratedLevel, rng: &mut StdRng) {
+    for room in &level.rooms {
+        if rng.gen::<f32>() < LIT_ROOM_CHANCE {
+            for x in room.x1..=room.x2 {
+                for y in room.y1..=room.y2 {
+                    level.map.set_lit(x, y, true);
+                }
+            }
+        }
+    }
+
+    let map = &level.map;
+    let beside_floor = |x: i32, y: i32| {
+        [(0, -1), (0, 1), (-1, 0), (1, 0)].iter().any(|&(dx, dy)| {
+            map.get(x + dx, y + dy)
+                .is_some_and(|tile| tile.kind() == TileKind::Floor)
+        })
+    };
+    let mut walls: Vec<_> = map
+        .iter()
+        .filter(|&((x, y), tile)| tile.kind() == TileKind::Wall && beside_floor(x, y))
+        .map(|(location, _)| location)
+        .collect();
+    for _ in 0..MAX_WALL_TORCHES {
+        if walls.is_empty() {
+            break;
+        }
+        let (x, y) = walls.swap_remove(rng.gen_range(0, walls.len()));
+        level.map.set_kind(x, y, TileKind::WallTorch);
+    }
+}
+
+/// Gives some rooms a theme and furnishes them for it. The rooms the player
+/// starts in and leaves from are left plain.
+pub fn theme_rooms(level: &mut GeneratedLevel, rng: &mut StdRng) {
+    for area in &mut level.spawn_areas {
+        let room = match area.room {
+            Some(room) => room,
+            None => continue,
+        };
+        let tiles = room.interior();
+        if tiles.contains(&level.player_start) || tiles.contains(&level.stairs) {
+            continue;
+        }
+        if rng.gen::<f32>() < ROOM_THEME_CHANCE {
+            let theme = themes::THEMES[rng.gen_range(0, themes::THEMES.len())];
+            area.theme = Some(theme);
+            let features = themes::decorate(room, theme, &mut level.map, rng);
+            level.features.extend(features);
+        }
+    }
+}
+
+/// Turns a few doors into secret doors and hides a few straight stretches of
+/// tunnel behind secret corridor tiles. A secret is only kept if the stairs
+/// can still be reached without finding it.
+pub fn hide_secrets(level: &mut GeneratedLevel, rng: &mut StdRng) {
+    let (start, stairs) = (level.player_start, level.stairs);
+    let hide = |map: &mut Map, x: i32, y: i32, secret: TileKind| {
+        let kind = map[(x, y)].kind();
+        map.set_kind(x, y, secret);
+        if !reachable_without_secrets(map, start, stairs) {
+            map.set_kind(x, y, kind);
+        }
+    };
+
+    let doors: Vec<_> = level
+        .map
+        .iter()
+        .filter(|(_, tile)| tile.kind() == TileKind::ClosedDoor)
+        .map(|(location, _)| location)
+        .collect();
+    for (x, y) in doors {
+        if rng.gen::<f32>() < SECRET_DOOR_CHANCE {
+            hide(&mut level.map, x, y, TileKind::SecretDoor);
+        }
+    }
+
+    let is_floor = |map: &Map, x: i32, y: i32| {
+        map.get(x, y)
+            .is_some_and(|tile| tile.kind() == TileKind::Floor)
+    };
+    let is_wall = |map: &Map, x: i32, y: i32| {
+        map.get(x, y)
+            .is_some_and(|tile| tile.kind() == TileKind::Wall)
+    };
+    let in_room = |x: i32, y: i32| {
+        level
+            .rooms
+            .iter()
+            .any(|room| x > room.x1 && x < room.x2 && y > room.y1 && y < room.y2)
+    };
+    let special = |location: &(i32, i32)| {
+        *location == level.player_start
+            || *location == level.stairs
+            || level.monster_spots.contains(location)
+            || level.item_spots.contains(location)
+    };
+    let mut tunnels: Vec<_> = level
+        .map
+        .coords()
+        .filter(|&(x, y)| {
+            let map = &level.map;
+            let horizontal = is_floor(map, x - 1, y)
+                && is_floor(map, x + 1, y)
+                && is_wall(map, x, y - 1)
+                && is_wall(map, x, y + 1);
+            let vertical = is_floor(map, x, y - 1)
+                && is_floor(map, x, y + 1)
+                && is_wall(map, x - 1, y)
+                && is_wall(map, x + 1, y);
+            is_floor(map, x, y) && (horizontal || vertical) && !in_room(x, y)
+        })
+        .filter(|location| !special(location))
+        .collect();
+
+    for _ in 0..MAX_SECRET_CORRIDORS {
+        if tunnels.is_empty() {
+            break;
+        }
+        let (x, y) = tunnels.swap_remove(rng.gen_range(0, tunnels.len()));
+        hide(&mut level.map, x, y, TileKind::SecretCorridor);
+    }
+}
+
+/// Hangs a closed door in every one-tile gap a tunnel has made in the walls
+/// of `room`.
+pub fn place_doors(room: Rect, map: &mut Map) {
+    let is_wall = |map: &Map, x: i32, y: i32| map[(x, y)].kind() == TileKind::Wall;
+
+    for x in (room.x1 + 1)..room.x2 {
+        for &y in &[room.y1, room.y2] {
+            if !map[(x, y)].blocked() && is_wall(map, x - 1, y) && is_wall(map, x + 1, y) {
+                map.set_kind(x, y, TileKind::ClosedDoor);
+            }
+        }
+    }
+    for y in (room.y1 + 1)..room.y2 {
+        for &x in &[room.x1, room.x2] {
+            if !map[(x, y)].blocked() && is_wall(map, x, y - 1) && is_wall(map, x, y + 1) {
+                map.set_kind(x, y, TileKind::ClosedDoor);
+            }
+        }
+    }
+}
+
+/// Tries random spots until `prefab`, with a one tile margin, lands entirely
+/// on wall. Returns whether it was placed.
+pub fn place_prefab(prefab: &Prefab, level: &mut GeneratedLevel, rng: &mut StdRng) -> bool {
+    let width = level.map.width();
+    let height = level.map.height();
+    if prefab.width + 2 > width || prefab.height + 2 > height {
+        return false;
+    }
+
+    for _ in 0..PREFAB_PLACEMENT_ATTEMPTS {
+        let left = rng.gen_range(1, width - prefab.width);
+        let top = rng.gen_range(1, height - prefab.height);
+
+        let fits = ((left - 1)..=(left + prefab.width)).all(|x| {
+            ((top - 1)..=(top + prefab.height)).all(|y| level.map[(x, y)].kind() == TileKind::Wall)
+        });
+        if !fits {
+            continue;
+        }
+
+        for x in 0..prefab.width {
+            for y in 0..prefab.height {
+                let (map_x, map_y) = (left + x, top + y);
+                match prefab.get(x, y) {
+                    PrefabTile::Keep => {}
+                    PrefabTile::Terrain(kind) => level.map.set_kind(map_x, map_y, kind),
+                    PrefabTile::Monster => {
+                        level.map.set_kind(map_x, map_y, TileKind::Floor);
+                        level.monster_spots.push((map_x, map_y));
+                    }
+                    PrefabTile::Item => {
+                        level.map.set_kind(map_x, map_y, TileKind::Floor);
+                        level.item_spots.push((map_x, map_y));
+                    }
+                }
+            }
+        }
+        return true;
+    }
+
+    false
+}
+
+/// Flood fills the walkable tiles of `map` from `start`, moving in the four
+/// directions the player can. Closed doors count as walkable. The result
+/// lines up with `map.iter()`.
+pub fn reachable_from(map: &Map, start: (i32, i32)) -> Vec<bool> {
+    flood_fill(map, start, Tile::traversable)
+}
+
+/// Whether `to` can be walked to from `start` without finding any secret
+/// on the way.
+pub fn reachable_without_secrets(map: &Map, start: (i32, i32), to: (i32, i32)) -> bool {
+    let reachable = flood_fill(map, start, |tile| {
+        tile.traversable() && tile.kind().revealed().is_none()
+    });
+    reachable[(to.1 * map.width() + to.0) as usize]
+}
+
+fn flood_fill(map: &Map, start: (i32, i32), passable: impl Fn(&Tile) -> bool) -> Vec<bool> {
+    let index = |(x, y): (i32, i32)| (y * map.width() + x) as usize;
+    let mut reachable = vec![false; (map.width() * map.height()) as usize];
+    let mut queue = VecDeque::new();
+
+    reachable[index(start)] = true;
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        for &(dx, dy) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let next = (x + dx, y + dy);
+            let passable = map.get(next.0, next.1).is_some_and(&passable);
+            if passable && !reachable[index(next)] {
+                reachable[index(next)] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Floor tiles of `map` that can't be walked to from `start`.
+pub fn unreachable_floor(map: &Map, start: (i32, i32)) -> Vec<(i32, i32)> {
+    map.iter()
+        .zip(reachable_from(map, start))
+        .filter(|&((_, tile), reachable)| tile.traversable() && !reachable)
+        .map(|((coords, _), _)| coords)
+        .collect()
+}
+
+/// Joins every cut-off pocket of floor to the nearest reachable tile until
+/// the whole map can be walked from `start`.
+pub fn connect_unreachable(map: &mut Map, start: (i32, i32), rng: &mut StdRng) {
+    loop {
+        let unreachable = unreachable_floor(map, start);
+        let (lost_x, lost_y) = match unreachable.first() {
+            Some(&tile) => tile,
+            None => return,
+        };
+
+        let nearest = map
+            .coords()
+            .zip(reachable_from(map, start))
+            .filter(|&(_, reachable)| reachable)
+            .map(|(coords, _)| coords)
+            .min_by_key(|&(x, y)| (x - lost_x).pow(2) + (y - lost_y).pow(2))
+            .unwrap_or(start);
+
+        create_tunnel_between(nearest, (lost_x, lost_y), map, rng);
+    }
+}
+
+/// Randomly scattered rectangular rooms, each joined to the one before it.
+pub struct RoomsGenerator;
+
+impl MapGenerator for RoomsGenerator {
+    fn name(&self) -> &'static str {
+        "rooms"
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut StdRng) -> GeneratedLevel {
+        let mut map = Map::new(width, height, Tile::wall());
+
+        let mut rooms: Vec<Rect> = vec![];
+
+        for _ in 0..MAX_ROOMS {
+            let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            let x = rng.gen_range(0, width - w);
+            let y = rng.gen_range(0, height - h);
+
+            let new_room = Rect::new(x, y, w, h);
+
+            let rooms_intersect = rooms
+                .iter()
+                .any(|other_room| new_room.intersects_with(other_room));
+
+            if !rooms_intersect {
+                create_room(new_room, &mut map);
+
+                if !rooms.is_empty() {
+                    let previous = rooms[rooms.len() - 1].center();
+                    create_tunnel_between(previous, new_room.center(), &mut map, rng);
+                }
+                rooms.push(new_room);
+            }
+        }
+
+        level_from_rooms(map, rooms)
+    }
+}
+
+/// Binary space partitioning: the map is split recursively, every leaf gets
+/// one room and sibling partitions are joined together.
+pub struct BspGenerator;
+
+impl MapGenerator for BspGenerator {
+    fn name(&self) -> &'static str {
+        "bsp"
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut StdRng) -> GeneratedLevel {
+        let mut map = Map::new(width, height, Tile::wall());
+
+        let mut rooms = vec![];
+        let area = Rect::new(0, 0, width - 1, height - 1);
+        split_partition(area, 0, &mut map, &mut rooms, rng);
+
+        level_from_rooms(map, rooms)
+    }
+}
+
+/// Recursively splits `area` in two until the pieces are too small or deep
+/// enough, carves one room per leaf and joins each pair of siblings. Returns
+/// the centre of a room inside `area` for the parent to tunnel to.
+fn split_partition(
+    area: Rect,
+    depth: i32,
+    map: &mut Map,
+    rooms: &mut Vec<Rect>,
+    rng: &mut StdRng,
+) -> (i32, i32) {
+    let width = area.x2 - area.x1;
+    let height = area.y2 - area.y1;
+    let can_split_x = width >= 2 * BSP_MIN_LEAF_SIZE;
+    let can_split_y = height >= 2 * BSP_MIN_LEAF_SIZE;
+
+    if depth >= BSP_MAX_DEPTH || !(can_split_x || can_split_y) {
+        let w = rng.gen_range(ROOM_MIN_SIZE, cmp::min(ROOM_MAX_SIZE, width) + 1);
+        let h = rng.gen_range(ROOM_MIN_SIZE, cmp::min(ROOM_MAX_SIZE, height) + 1);
+        let x = rng.gen_range(area.x1, area.x2 - w + 1);
+        let y = rng.gen_range(area.y1, area.y2 - h + 1);
+
+        let room = Rect::new(x, y, w, h);
+        create_room(room, map);
+        rooms.push(room);
+        return room.center();
+    }
+
+    let split_x = if can_split_x && can_split_y {
+        width > height
+    } else {
+        can_split_x
+    };
+
+    let (first, second) = if split_x {
+        let split = rng.gen_range(area.x1 + BSP_MIN_LEAF_SIZE, area.x2 - BSP_MIN_LEAF_SIZE + 1);
+        (
+            Rect::new(area.x1, area.y1, split - area.x1, height),
+            Rect::new(split, area.y1, area.x2 - split, height),
+        )
+    } else {
+        let split = rng.gen_range(area.y1 + BSP_MIN_LEAF_SIZE, area.y2 - BSP_MIN_LEAF_SIZE + 1);
+        (
+            Rect::new(area.x1, area.y1, width, split - area.y1),
+            Rect::new(area.x1, split, width, area.y2 - split),
+        )
+    };
+
+    let first_center = split_partition(first, depth + 1, map, rooms, rng);
+    let second_center = split_partition(second, depth + 1, map, rooms, rng);
+    create_tunnel_between(first_center, second_center, map, rng);
+
+    if rng.gen() {
+        first_center
+    } else {
+        second_center
+    }
+}
+
+/// Organic caves grown from random noise by cellular-automata smoothing. Only
+/// the largest connected region is kept.
+pub struct CaveGenerator;
+
+impl MapGenerator for CaveGenerator {
+    fn name(&self) -> &'static str {
+        "cave"
+    }
+
+    /// Caves spread out wider than the screen and keep growing the deeper
+    /// the player goes.
+    fn map_size(&self, dungeon_level: u32) -> (i32, i32) {
+        let depth = dungeon_level as i32;
+        (
+            cmp::min(100 + 10 * depth, CAVE_MAX_MAP_WIDTH),
+            cmp::min(50 + 5 * depth, CAVE_MAX_MAP_HEIGHT),
+        )
+    }
+
+    fn generate(&self, width: i32, height: i32, rng: &mut StdRng) -> GeneratedLevel {
+        // A small map may never grow enough floor, so after a few tries it
+        // gets rooms instead.
+        let floor = (0..CAVE_MAX_ATTEMPTS)
+            .map(|_| largest_open_region(&make_cave_walls(width, height, rng)))
+            .find(|region| region.len() >= CAVE_MIN_FLOOR);
+        let floor = match floor {
+            Some(floor) => floor,
+            None => return RoomsGenerator.generate(width, height, rng),
+        };
+
+        let mut map = Map::new(width, height, Tile::wall());
+        for &(x, y) in &floor {
+            map.set_kind(x, y, TileKind::Floor);
+        }
+
+        let (player_x, player_y) = floor[rng.gen_range(0, floor.len())];
+        let stairs = *floor
+            .iter()
+            .max_by_key(|&&(x, y)| (x - player_x).pow(2) + (y - player_y).pow(2))
+            .unwrap();
+
+        let mut areas: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+        for &(x, y) in &floor {
+            areas
+                .entry((x / CAVE_AREA_SIZE, y / CAVE_AREA_SIZE))
+                .or_default()
+                .push((x, y));
+        }
+        let mut spawn_areas: Vec<_> = areas
+            .into_iter()
+            .filter(|(_, tiles)| tiles.len() >= CAVE_MIN_AREA_TILES)
+            .collect();
+        // HashMap order is not stable between runs, so sort to keep seeds reproducible.
+        spawn_areas.sort_by_key(|&(cell, _)| cell);
+
+        GeneratedLevel {
+            map,
+            player_start: (player_x, player_y),
+            stairs,
+            rooms: vec![],
+            spawn_areas: spawn_areas
+                .into_iter()
+                .map(|(_, tiles)| SpawnArea {
+                    tiles,
+                    room: None,
+                    theme: None,
+                })
+                .collect(),
+            monster_spots: vec![],
+            item_spots: vec![],
+            features: vec![],
+        }
+    }
+}
+
+/// Fills the map with random noise and smooths it into caves. `true` marks a
+/// wall; the outer border is always wall.
+fn make_cave_walls(width: i32, height: i32, rng: &mut StdRng) -> Vec<Vec<bool>> {
+    let mut walls = vec![vec![true; height as usize]; width as usize];
+    for x in 1..width - 1 {
+        for y in 1..height - 1 {
+            walls[x as usize][y as usize] = rng.gen::<f32>() < CAVE_WALL_CHANCE;
+        }
+    }
+
+    for _ in 0..CAVE_SMOOTHING_PASSES {
+        let previous = walls.clone();
+        for x in 1..(width - 1) as usize {
+            for y in 1..(height - 1) as usize {
+                let neighbours = (x - 1..=x + 1)
+                    .flat_map(|nx| (y - 1..=y + 1).map(move |ny| (nx, ny)))
+                    .filter(|&(nx, ny)| (nx, ny) != (x, y) && previous[nx][ny])
+                    .count();
+                walls[x][y] = neighbours > 4 || (neighbours == 4 && previous[x][y]);
+            }
+        }
+    }
+
+    walls
+}
+
+/// Flood fills every open area of `walls` and returns the tiles of the
+/// biggest one. The border of `walls` must be solid.
+fn largest_open_region(walls: &[Vec<bool>]) -> Vec<(i32, i32)> {
+    let width = walls.len();
+    let height = walls[0].len();
+    let mut visited = vec![vec![false; height]; width];
+    let mut largest = vec![];
+
+    for start_x in 0..width {
+        for start_y in 0..height {
+            if walls[start_x][start_y] || visited[start_x][start_y] {
+                continue;
+            }
+
+            let mut region = vec![];
+            let mut queue = VecDeque::new();
+            visited[start_x][start_y] = true;
+            queue.push_back((start_x as i32, start_y as i32));
+
+            while let Some((x, y)) = queue.pop_front() {
+                region.push((x, y));
+                for &(dx, dy) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (nx, ny) = ((x + dx) as usize, (y + dy) as usize);
+                    if !walls[nx][ny] && !visited[nx][ny] {
+                        visited[nx][ny] = true;
+                        queue.push_back((x + dx, y + dy));
+                    }
+                }
+            }
+
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+    }
+
+    largest
+}
+
+/// The player starts in the first room, the stairs go in the last one and
+/// every room is a spawn area.
+fn level_from_rooms(map: Map, rooms: Vec<Rect>) -> GeneratedLevel {
+    GeneratedLevel {
+        map,
+        player_start: rooms[0].center(),
+        stairs: rooms[rooms.len() - 1].center(),
+        spawn_areas: rooms
+            .iter()
+            .map(|&room| SpawnArea {
+                tiles: room.interior(),
+                room: Some(room),
+                theme: None,
+            })
+            .collect(),
+        rooms,
+        monster_spots: vec![],
+        item_spots: vec![],
+        features: vec![],
+    }
+}
+
+pub fn create_room(room: Rect, map: &mut Map) {
+    for x in (room.x1 + 1)..room.x2 {
+        for y in (room.y1 + 1)..room.y2 {
+            map.set_kind(x, y, TileKind::Floor);
+        }
+    }
+}
+
+pub fn create_horizontal_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map) {
+    for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
+        map.set_kind(x, y, TileKind::Floor);
+    }
+}
+
+pub fn create_vertical_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map) {
+    for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
+        map.set_kind(x, y, TileKind::Floor);
+    }
+}
+
+pub fn create_tunnel_between(from: (i32, i32), to: (i32, i32), map: &mut Map, rng: &mut StdRng) {
+    let ((from_x, from_y), (to_x, to_y)) = (from, to);
+    if rng.gen() {
+        create_horizontal_tunnel(from_x, to_x, from_y, map);
+        create_vertical_tunnel(from_y, to_y, to_x, map);
+    } else {
+        create_vertical_tunnel(from_y, to_y, from_x, map);
+        create_horizontal_tunnel(from_x, to_x, to_y, map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use std::path::Path;
+
+    const WIDTH: i32 = 80;
+    const HEIGHT: i32 = 43;
+
+    #[test]
+    fn every_generator_is_fully_connected() {
+        let prefabs = Prefab::load_all(Path::new("res/prefabs")).unwrap();
+        for generator in generators() {
+            for seed in 0..1000 {
+                let mut rng = StdRng::from_seed(&[seed][..]);
+                let level = build_level(generator.as_ref(), &prefabs, WIDTH, HEIGHT, &mut rng);
+
+                assert!(
+                    unreachable_floor(&level.map, level.player_start).is_empty(),
+                    "{} left unreachable floor with seed {}",
+                    generator.name(),
+                    seed
+                );
+                let (stairs_x, stairs_y) = level.stairs;
+                assert!(!level.map[(stairs_x, stairs_y)].blocked());
+            }
+        }
+    }
+
+    #[test]
+    fn every_generator_fills_small_and_large_maps() {
+        let prefabs = Prefab::load_all(Path::new("res/prefabs")).unwrap();
+        let sizes = [
+            (MIN_MAP_WIDTH, MIN_MAP_HEIGHT),
+            (CAVE_MAX_MAP_WIDTH, CAVE_MAX_MAP_HEIGHT),
+        ];
+        for generator in generators() {
+            for &(width, height) in &sizes {
+                for seed in 0..20 {
+                    let mut rng = StdRng::from_seed(&[seed][..]);
+                    let level = build_level(generator.as_ref(), &prefabs, width, height, &mut rng);
+
+                    assert_eq!((level.map.width(), level.map.height()), (width, height));
+                    assert!(
+                        unreachable_floor(&level.map, level.player_start).is_empty(),
+                        "{} left unreachable floor on a {}x{} map with seed {}",
+                        generator.name(),
+                        width,
+                        height,
+                        seed
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn caves_too_small_for_enough_floor_fall_back_to_rooms() {
+        let mut rng = StdRng::from_seed(&[0][..]);
+        let level = CaveGenerator.generate(20, 20, &mut rng);
+
+        assert!(!level.rooms.is_empty());
+        assert!(unreachable_floor(&level.map, level.player_start).is_empty());
+    }
+
+    #[test]
+    fn secrets_stay_off_the_important_tiles() {
+        let prefabs = Prefab::load_all(Path::new("res/prefabs")).unwrap();
+        let mut secrets = 0;
+        for seed in 0..100 {
+            let mut rng = StdRng::from_seed(&[seed][..]);
+            let level = build_level(&RoomsGenerator, &prefabs, WIDTH, HEIGHT, &mut rng);
+
+            for (location, tile) in level.map.iter() {
+                if tile.kind().revealed().is_some() {
+                    secrets += 1;
+                    assert_ne!(location, level.player_start);
+                    assert_ne!(location, level.stairs);
+                    assert!(!level.item_spots.contains(&location));
+                }
+            }
+        }
+        assert!(secrets > 0);
+    }
+
+    #[test]
+    fn torches_hang_on_walls_beside_floor() {
+        let mut map = Map::new(WIDTH, HEIGHT, Tile::wall());
+        create_room(Rect::new(10, 10, 6, 6), &mut map);
+        let mut level = GeneratedLevel {
+            map,
+            player_start: (12, 12),
+            stairs: (13, 13),
+            rooms: vec![],
+            spawn_areas: vec![],
+            monster_spots: vec![],
+            item_spots: vec![],
+            features: vec![],
+        };
+        let mut rng = StdRng::from_seed(&[0][..]);
+        light_level(&mut level, &mut rng);
+
+        let torches: Vec<_> = level
+            .map
+            .iter()
+            .filter(|(_, tile)| tile.kind() == TileKind::WallTorch)
+            .map(|(location, _)| location)
+            .collect();
+        assert_eq!(torches.len(), MAX_WALL_TORCHES);
+        for (x, y) in torches {
+            assert!((10..=16).contains(&x) && (10..=16).contains(&y));
+            assert!(!(x > 10 && x < 16 && y > 10 && y < 16));
+        }
+    }
+
+    #[test]
+    fn rooms_generator_only_keeps_carved_rooms() {
+        for seed in 0..1000 {
+            let mut rng = StdRng::from_seed(&[seed][..]);
+            let level = RoomsGenerator.generate(WIDTH, HEIGHT, &mut rng);
+
+            for (index, room) in level.rooms.iter().enumerate() {
+                assert!(level.rooms[index + 1..]
+                    .iter()
+                    .all(|other| !room.intersects_with(other)));
+            }
+            assert!(unreachable_floor(&level.map, level.player_start).is_empty());
+        }
+    }
+
+    #[test]
+    fn repair_joins_isolated_rooms() {
+        let mut map = Map::new(WIDTH, HEIGHT, Tile::wall());
+        create_room(Rect::new(1, 1, 6, 6), &mut map);
+        create_room(Rect::new(40, 20, 6, 6), &mut map);
+        create_room(Rect::new(60, 2, 8, 8), &mut map);
+        let start = (3, 3);
+        assert_eq!(unreachable_floor(&map, start).len(), 25 + 49);
+
+        let mut rng = StdRng::from_seed(&[0][..]);
+        connect_unreachable(&mut map, start, &mut rng);
+
+        assert!(unreachable_floor(&map, start).is_empty());
+    }
+
+    #[test]
+    fn secrets_never_cut_the_player_off_from_the_stairs() {
+        let prefabs = Prefab::load_all(Path::new("res/prefabs")).unwrap();
+        for generator in generators() {
+            for seed in 0..300 {
+                let mut rng = StdRng::from_seed(&[seed][..]);
+                let level = build_level(generator.as_ref(), &prefabs, WIDTH, HEIGHT, &mut rng);
+
+                assert!(
+                    reachable_without_secrets(&level.map, level.player_start, level.stairs),
+                    "{} hid the way to the stairs with seed {}",
+                    generator.name(),
+                    seed
+                );
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use tcod::colors::*;
+use crate::color::*;
 
 pub struct Messages {
     messages: Vec<(String, Color)>,
@@ -0,0 +1,208 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::entity::{self, Entity};
+use crate::light::Light;
+use crate::spawn::{self, Transition};
+use crate::themes::{self, RoomTheme};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum MonsterAi {
+    Basic,
+}
+
+/// One entry of the monsters file. `spawn_weights` work like any other
+/// spawn table entry; `min_depth` and `max_depth` bound where it can spawn
+/// at all.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterDef {
+    pub name: String,
+    pub glyph: char,
+    pub color: (u8, u8, u8),
+    pub hp: i32,
+    pub defense: i32,
+    pub power: i32,
+    pub ai: MonsterAi,
+    pub spawn_weights: Vec<Transition>,
+    #[serde(default = "spawn::first_level")]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    /// How far it sees; 0 makes it blind.
+    #[serde(default = "default_sight_radius")]
+    pub sight_radius: i32,
+    /// How many turns it keeps hunting the player after losing sight of
+    /// them.
+    #[serde(default = "default_memory")]
+    pub memory: i32,
+    /// Light it gives off, for monsters that burn or glow.
+    #[serde(default)]
+    pub light: Option<Light>,
+    /// Room themes this monster is especially likely to turn up in.
+    #[serde(default)]
+    pub themes: Vec<RoomTheme>,
+}
+
+fn default_sight_radius() -> i32 {
+    8
+}
+
+fn default_memory() -> i32 {
+    10
+}
+
+impl MonsterDef {
+    pub fn spawn(&self, x: i32, y: i32) -> Entity {
+        let (r, g, b) = self.color;
+        let mut monster = Entity::new(x, y, self.glyph, &self.name, Color::new(r, g, b), true);
+        monster.fighter = Some(entity::Fighter {
+            max_hp: self.hp,
+            hp: self.hp,
+            defense: self.defense,
+            power: self.power,
+            on_death: entity::DeathCallback::Monster,
+        });
+        monster.ai = Some(match self.ai {
+            MonsterAi::Basic => entity::AI::Basic,
+        });
+        monster.senses = Some(entity::Senses::new(self.sight_radius, self.memory));
+        monster.light = self.light;
+        monster.alive = true;
+        monster
+    }
+
+    /// `spawn_weights` with the depth range folded in and boosted for a
+    /// favoured room theme, ready for a spawn table.
+    pub fn weights(&self, theme: Option<RoomTheme>) -> Vec<Transition> {
+        let weights = spawn::depth_limited(&self.spawn_weights, self.min_depth, self.max_depth);
+        themes::favour(weights, &self.themes, theme)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is empty".into());
+        }
+        if self.glyph.is_whitespace() {
+            return Err("glyph cannot be blank".into());
+        }
+        if self.hp <= 0 {
+            return Err(format!("hp must be above 0, got {}", self.hp));
+        }
+        if self.defense < 0 || self.power < 0 {
+            return Err("defense and power cannot be negative".into());
+        }
+        if self.sight_radius < 0 || self.memory < 0 {
+            return Err("sight_radius and memory cannot be negative".into());
+        }
+        if let Some(light) = self.light {
+            light.validate()?;
+        }
+        spawn::check_weights(&self.spawn_weights, self.min_depth, self.max_depth)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<MonsterDef>, String> {
+    let monsters: Vec<MonsterDef> = ron::from_str(text).map_err(|error| error.to_string())?;
+    if monsters.is_empty() {
+        return Err("no monsters defined".into());
+    }
+
+    let mut names = HashSet::new();
+    for (index, monster) in monsters.iter().enumerate() {
+        monster
+            .validate()
+            .map_err(|error| format!("monster {} '{}': {}", index + 1, monster.name, error))?;
+        if !names.insert(&monster.name) {
+            return Err(format!(
+                "monster {} '{}': name is already taken",
+                index + 1,
+                monster.name
+            ));
+        }
+    }
+    Ok(monsters)
+}
+
+pub fn load(path: &Path) -> Result<Vec<MonsterDef>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+    parse(&text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOBLIN: &str = r#"[(
+        name: "goblin",
+        glyph: 'g',
+        color: (0, 200, 0),
+        hp: 5,
+        defense: 0,
+        power: 2,
+        ai: Basic,
+        spawn_weights: [(level: 1, value: 10), (level: 3, value: 40)],
+        min_depth: 2,
+        max_depth: Some(4),
+    )]"#;
+
+    #[test]
+    fn shipped_monsters_load() {
+        let monsters = load(Path::new("res/monsters.ron")).unwrap();
+        assert!(monsters.iter().any(|monster| monster.name == "orc"));
+    }
+
+    #[test]
+    fn senses_default_when_left_out() {
+        let goblin = &parse(GOBLIN).unwrap()[0];
+        let senses = goblin.spawn(0, 0).senses.unwrap();
+        assert_eq!((senses.sight_radius, senses.memory), (8, 10));
+
+        let blind = parse(&GOBLIN.replace("hp: 5", "hp: 5, sight_radius: 0")).unwrap();
+        assert_eq!(blind[0].spawn(0, 0).senses.unwrap().sight_radius, 0);
+    }
+
+    #[test]
+    fn weights_respect_the_depth_range() {
+        let goblin = &parse(GOBLIN).unwrap()[0];
+        let weights = goblin.weights(None);
+        let at = |level| spawn::from_dungeon_level(&weights, level);
+        assert_eq!((1..=5).map(at).collect::<Vec<_>>(), vec![0, 10, 40, 40, 0]);
+    }
+
+    #[test]
+    fn parse_rejects_bad_entries() {
+        let error = parse(&GOBLIN.replace("hp: 5", "hp: 0")).unwrap_err();
+        assert_eq!(error, "monster 1 'goblin': hp must be above 0, got 0");
+
+        let error = parse(&GOBLIN.replace("Some(4)", "Some(1)")).unwrap_err();
+        assert_eq!(
+            error,
+            "monster 1 'goblin': max_depth 1 is below min_depth 2"
+        );
+
+        let twice = format!("[{0}, {0}]", &GOBLIN[1..GOBLIN.len() - 1]);
+        let error = parse(&twice).unwrap_err();
+        assert_eq!(error, "monster 2 'goblin': name is already taken");
+
+        let error = parse(&GOBLIN.replace("hp: 5", "hp: 5, sight_radius: -1")).unwrap_err();
+        assert_eq!(
+            error,
+            "monster 1 'goblin': sight_radius and memory cannot be negative"
+        );
+
+        let dark = "hp: 5, light: Some((radius: 0, color: (255, 90, 0), falloff: Linear))";
+        let error = parse(&GOBLIN.replace("hp: 5", dark)).unwrap_err();
+        assert_eq!(
+            error,
+            "monster 1 'goblin': light radius must be above 0, got 0"
+        );
+
+        assert!(parse(&GOBLIN.replace("ai: Basic", "ai: Sneaky")).is_err());
+        assert!(parse(&GOBLIN.replace("hp: 5", "hp: 5, speed: 3")).is_err());
+    }
+}
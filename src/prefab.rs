@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::Path;
+
+use crate::tile::TileKind;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrefabTile {
+    Keep,
+    Terrain(TileKind),
+    Monster,
+    Item,
+}
+
+impl PrefabTile {
+    fn from_char(char: char) -> Option<Self> {
+        use PrefabTile::*;
+        match char {
+            ' ' => Some(Keep),
+            '#' => Some(Terrain(TileKind::Wall)),
+            '.' => Some(Terrain(TileKind::Floor)),
+            '+' => Some(Terrain(TileKind::ClosedDoor)),
+            '\'' => Some(Terrain(TileKind::OpenDoor)),
+            '~' => Some(Terrain(TileKind::Water)),
+            '=' => Some(Terrain(TileKind::Lava)),
+            '"' => Some(Terrain(TileKind::Grass)),
+            ':' => Some(Terrain(TileKind::Rubble)),
+            '*' => Some(Terrain(TileKind::WallTorch)),
+            'M' => Some(Monster),
+            'I' => Some(Item),
+            _ => None,
+        }
+    }
+}
+
+/// A hand-made room read from an ASCII template. `tiles` is indexed
+/// `[y][x]`, the way the template is written.
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    tiles: Vec<Vec<PrefabTile>>,
+}
+
+impl Prefab {
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let lines: Vec<&str> = text.lines().collect();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut tiles = vec![];
+        for (y, line) in lines.iter().enumerate() {
+            let mut row = vec![PrefabTile::Keep; width];
+            for (x, char) in line.chars().enumerate() {
+                row[x] = PrefabTile::from_char(char).ok_or_else(|| {
+                    format!(
+                        "prefab '{}' line {} column {}: unknown tile '{}'",
+                        name,
+                        y + 1,
+                        x + 1,
+                        char
+                    )
+                })?;
+            }
+            tiles.push(row);
+        }
+
+        let has_floor = tiles.iter().flatten().any(|&tile| match tile {
+            PrefabTile::Keep => false,
+            PrefabTile::Terrain(kind) => kind.terrain().passable,
+            PrefabTile::Monster | PrefabTile::Item => true,
+        });
+        if !has_floor {
+            return Err(format!("prefab '{}' has no floor", name));
+        }
+
+        Ok(Prefab {
+            name: name.into(),
+            width: width as i32,
+            height: tiles.len() as i32,
+            tiles,
+        })
+    }
+
+    /// Loads every `.txt` template in `dir`, named after its file.
+    pub fn load_all(dir: &Path) -> Result<Vec<Self>, String> {
+        let entries = fs::read_dir(dir)
+            .map_err(|error| format!("cannot read {}: {}", dir.display(), error))?;
+
+        let mut paths = vec![];
+        for entry in entries {
+            let path = entry
+                .map_err(|error| format!("cannot read {}: {}", dir.display(), error))?
+                .path();
+            if path.extension().is_some_and(|extension| extension == "txt") {
+                paths.push(path);
+            }
+        }
+        // read_dir order depends on the filesystem; keep it fixed so seeds
+        // pick the same prefabs everywhere.
+        paths.sort();
+
+        paths
+            .iter()
+            .map(|path| {
+                let text = fs::read_to_string(path)
+                    .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+                let name = path.file_stem().unwrap().to_string_lossy();
+                Prefab::parse(&name, &text)
+            })
+            .collect()
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> PrefabTile {
+        self.tiles[y as usize][x as usize]
+    }
+
+    /// Turned a quarter turn clockwise.
+    pub fn rotated(&self) -> Self {
+        let tiles = (0..self.width)
+            .map(|y| {
+                (0..self.height)
+                    .map(|x| self.get(y, self.height - 1 - x))
+                    .collect()
+            })
+            .collect();
+        Prefab {
+            name: self.name.clone(),
+            width: self.height,
+            height: self.width,
+            tiles,
+        }
+    }
+
+    /// Flipped left to right.
+    pub fn mirrored(&self) -> Self {
+        let tiles = self
+            .tiles
+            .iter()
+            .map(|row| row.iter().rev().cloned().collect())
+            .collect();
+        Prefab {
+            name: self.name.clone(),
+            width: self.width,
+            height: self.height,
+            tiles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pads_short_rows_with_keep() {
+        let prefab = Prefab::parse("test", "###\n#M\n#I#").unwrap();
+        assert_eq!((prefab.width, prefab.height), (3, 3));
+        assert_eq!(prefab.get(1, 1), PrefabTile::Monster);
+        assert_eq!(prefab.get(2, 1), PrefabTile::Keep);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_tiles() {
+        let error = Prefab::parse("test", "#.#\n#x#").unwrap_err();
+        assert_eq!(error, "prefab 'test' line 2 column 2: unknown tile 'x'");
+    }
+
+    #[test]
+    fn rotate_turns_clockwise() {
+        let prefab = Prefab::parse("test", "M..\n##I").unwrap().rotated();
+        assert_eq!((prefab.width, prefab.height), (2, 3));
+        assert_eq!(prefab.get(1, 0), PrefabTile::Monster);
+        assert_eq!(prefab.get(0, 2), PrefabTile::Item);
+    }
+
+    #[test]
+    fn mirror_flips_rows() {
+        let prefab = Prefab::parse("test", "M.#").unwrap().mirrored();
+        assert_eq!(prefab.get(0, 0), PrefabTile::Terrain(TileKind::Wall));
+        assert_eq!(prefab.get(2, 0), PrefabTile::Monster);
+    }
+}
@@ -21,7 +21,13 @@ impl Rect {
         let center_y = (self.y1 + self.y2) / 2;
         (center_x, center_y)
     }
-    
+
+    pub fn interior(&self) -> Vec<(i32, i32)> {
+        ((self.x1 + 1)..self.x2)
+            .flat_map(|x| ((self.y1 + 1)..self.y2).map(move |y| (x, y)))
+            .collect()
+    }
+
     pub fn intersects_with(&self, other: &Rect) -> bool {
         (self.x1 <= other.x2)
             && (self.x2 >= other.x1)
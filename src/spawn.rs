@@ -0,0 +1,162 @@
+use rand::{Rng, StdRng};
+use serde::Deserialize;
+
+/// From `level` down, a table entry has `value`, until a deeper transition
+/// takes over.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Transition {
+    pub level: u32,
+    pub value: u32,
+}
+
+/// The value of the deepest transition at or above `level`, or 0 before the
+/// first one.
+pub fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
+    table
+        .iter()
+        .rev()
+        .find(|transition| level >= transition.level)
+        .map_or(0, |transition| transition.value)
+}
+
+/// `weights` cut down to the depths from `min_depth` to `max_depth`, with a
+/// weight of 0 everywhere else.
+pub fn depth_limited(
+    weights: &[Transition],
+    min_depth: u32,
+    max_depth: Option<u32>,
+) -> Vec<Transition> {
+    let mut limited = vec![Transition {
+        level: min_depth,
+        value: from_dungeon_level(weights, min_depth),
+    }];
+    limited.extend(weights.iter().filter(|transition| {
+        transition.level > min_depth && max_depth.is_none_or(|max| transition.level <= max)
+    }));
+    if let Some(max_depth) = max_depth {
+        limited.push(Transition {
+            level: max_depth + 1,
+            value: 0,
+        });
+    }
+    limited
+}
+
+/// The checks shared by every data file entry with spawn weights and a depth
+/// range.
+pub fn check_weights(
+    weights: &[Transition],
+    min_depth: u32,
+    max_depth: Option<u32>,
+) -> Result<(), String> {
+    if weights.is_empty() {
+        return Err("spawn_weights is empty".into());
+    }
+    if !weights.windows(2).all(|pair| pair[0].level < pair[1].level) {
+        return Err("spawn_weights levels must go up".into());
+    }
+    if min_depth == 0 {
+        return Err("min_depth starts at 1".into());
+    }
+    if let Some(max_depth) = max_depth {
+        if max_depth < min_depth {
+            return Err(format!(
+                "max_depth {} is below min_depth {}",
+                max_depth, min_depth
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The default `min_depth` in the data files.
+pub fn first_level() -> u32 {
+    1
+}
+
+/// Things to spawn, each weighted by how deep the level is. An entry with
+/// weight 0 at some level never comes up there.
+pub struct SpawnTable<T> {
+    entries: Vec<(T, Vec<Transition>)>,
+}
+
+impl<T: Clone> SpawnTable<T> {
+    pub fn new() -> Self {
+        SpawnTable { entries: vec![] }
+    }
+
+    pub fn add(mut self, value: T, weights: &[Transition]) -> Self {
+        self.entries.push((value, weights.to_vec()));
+        self
+    }
+
+    /// A random entry in proportion to its weight at `level`, or `None` if
+    /// nothing can spawn there.
+    pub fn pick(&self, level: u32, rng: &mut StdRng) -> Option<T> {
+        let total: u32 = self
+            .entries
+            .iter()
+            .map(|(_, weights)| from_dungeon_level(weights, level))
+            .sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.gen_range(0, total);
+        for (value, weights) in &self.entries {
+            let weight = from_dungeon_level(weights, level);
+            if roll < weight {
+                return Some(value.clone());
+            }
+            roll -= weight;
+        }
+        unreachable!("roll is below the total weight")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const RISING: &[Transition] = &[
+        Transition {
+            level: 2,
+            value: 10,
+        },
+        Transition {
+            level: 5,
+            value: 30,
+        },
+    ];
+
+    #[test]
+    fn from_dungeon_level_uses_the_deepest_transition_reached() {
+        assert_eq!(from_dungeon_level(RISING, 1), 0);
+        assert_eq!(from_dungeon_level(RISING, 2), 10);
+        assert_eq!(from_dungeon_level(RISING, 4), 10);
+        assert_eq!(from_dungeon_level(RISING, 9), 30);
+    }
+
+    #[test]
+    fn pick_skips_entries_not_yet_unlocked() {
+        let table = SpawnTable::new()
+            .add("common", &[Transition { level: 1, value: 1 }])
+            .add("rare", RISING);
+        let mut rng = StdRng::from_seed(&[0][..]);
+
+        assert!((0..100).all(|_| table.pick(1, &mut rng) == Some("common")));
+        assert!((0..100).any(|_| table.pick(5, &mut rng) == Some("rare")));
+        assert_eq!(SpawnTable::<&str>::new().pick(1, &mut rng), None);
+    }
+
+    #[test]
+    fn depth_limited_is_zero_outside_the_range() {
+        let weights = depth_limited(RISING, 3, Some(6));
+        let at = |level| from_dungeon_level(&weights, level);
+        assert_eq!(
+            (1..=8).map(at).collect::<Vec<_>>(),
+            vec![0, 0, 10, 10, 30, 30, 0, 0]
+        );
+    }
+}
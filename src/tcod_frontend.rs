@@ -0,0 +1,114 @@
+use tcod::console::*;
+use tcod::input::{self, Event, KeyCode};
+
+use crate::color::{Color, BLACK};
+use crate::frontend::{Frontend, Input, Key};
+
+/// The game in a libtcod window.
+pub struct TcodFrontend {
+    root: Root,
+}
+
+impl TcodFrontend {
+    pub fn new(width: i32, height: i32, title: &str, fps: i32) -> Self {
+        let root = Root::initializer()
+            .font("res/arial10x10.png", FontLayout::Tcod)
+            .font_type(FontType::Greyscale)
+            .size(width, height)
+            .title(title)
+            .init();
+        tcod::system::set_fps(fps);
+        TcodFrontend { root }
+    }
+
+    fn in_window(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.root.width() && y < self.root.height()
+    }
+}
+
+fn tcod_color(color: Color) -> tcod::colors::Color {
+    tcod::colors::Color::new(color.r, color.g, color.b)
+}
+
+/// Letters and other printable keys arrive as text events; the key code
+/// events tcod sends alongside them are left out so each press counts once.
+fn key_from_tcod(key: input::Key) -> Option<Key> {
+    match key.code {
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        KeyCode::Escape => Some(Key::Escape),
+        KeyCode::Enter => Some(Key::Enter { alt: key.alt }),
+        KeyCode::Text => key.text().chars().next().map(Key::Char),
+        _ => None,
+    }
+}
+
+impl Frontend for TcodFrontend {
+    fn size(&self) -> (i32, i32) {
+        (self.root.width(), self.root.height())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.root.window_closed()
+    }
+
+    fn clear(&mut self) {
+        self.root.set_default_background(tcod_color(BLACK));
+        self.root.clear();
+    }
+
+    fn put_char(
+        &mut self,
+        x: i32,
+        y: i32,
+        glyph: char,
+        foreground: Color,
+        background: Option<Color>,
+    ) {
+        if !self.in_window(x, y) {
+            return;
+        }
+        match background {
+            Some(background) => {
+                self.root
+                    .put_char_ex(x, y, glyph, tcod_color(foreground), tcod_color(background))
+            }
+            None => {
+                self.root.set_char(x, y, glyph);
+                self.root.set_char_foreground(x, y, tcod_color(foreground));
+            }
+        }
+    }
+
+    fn present(&mut self) {
+        self.root.flush();
+    }
+
+    fn poll_input(&mut self) -> Option<Input> {
+        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
+            Some((_, Event::Mouse(mouse))) => Some(Input::Mouse(mouse.cx as i32, mouse.cy as i32)),
+            Some((_, Event::Key(key))) => key_from_tcod(key).map(Input::Key),
+            None => None,
+        }
+    }
+
+    fn wait_for_key(&mut self) -> Key {
+        loop {
+            let key = self.root.wait_for_keypress(true);
+            // A blocking wait sees the key code event, not the text one.
+            if key.code == KeyCode::Char {
+                return Key::Char(key.printable);
+            }
+            if let Some(key) = key_from_tcod(key) {
+                return key;
+            }
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = self.root.is_fullscreen();
+        self.root.set_fullscreen(!fullscreen);
+    }
+}
@@ -0,0 +1,321 @@
+use std::env;
+use std::io::{self, Stdout, Write};
+use std::panic;
+use std::time::Duration;
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEventKind,
+};
+use crossterm::style::{self, Print, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+
+use crate::color::{Color, BLACK, WHITE};
+use crate::frontend::{Frontend, Input, Key};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cell {
+    glyph: char,
+    foreground: Color,
+    background: Color,
+}
+
+const BLANK: Cell = Cell {
+    glyph: ' ',
+    foreground: WHITE,
+    background: BLACK,
+};
+
+/// The game drawn with ANSI escape codes in the terminal it was started
+/// from, for playing over SSH. Takes over the terminal until dropped.
+pub struct TerminalFrontend {
+    out: Stdout,
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+    /// What the terminal shows now, or `None` if it has to be drawn afresh.
+    /// Only cells that differ from it are written out.
+    shown: Option<Vec<Cell>>,
+    frame_time: Duration,
+    truecolor: bool,
+    closed: bool,
+}
+
+impl TerminalFrontend {
+    pub fn new(fps: i32) -> io::Result<Self> {
+        let (width, height) = terminal::size()?;
+        terminal::enable_raw_mode()?;
+        let mut frontend = TerminalFrontend {
+            out: io::stdout(),
+            width: 0,
+            height: 0,
+            cells: vec![],
+            shown: None,
+            frame_time: Duration::from_millis(1000 / fps.max(1) as u64),
+            truecolor: env::var("COLORTERM")
+                .is_ok_and(|value| value.contains("truecolor") || value.contains("24bit")),
+            closed: false,
+        };
+        frontend.resize(width, height);
+        // Dropping the frontend puts the terminal back if this fails.
+        execute!(
+            frontend.out,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            event::EnableMouseCapture
+        )?;
+
+        // A panic message printed on the alternate screen in raw mode would
+        // be wiped out or garbled, so put the terminal back first.
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
+
+        Ok(frontend)
+    }
+
+    fn resize(&mut self, width: u16, height: u16) {
+        self.width = width as i32;
+        self.height = height as i32;
+        self.cells = vec![BLANK; width as usize * height as usize];
+        self.shown = None;
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        if self.shown.is_none() {
+            queue!(self.out, terminal::Clear(ClearType::All))?;
+        }
+
+        let mut colors = None;
+        let mut cursor = None;
+        for (index, &cell) in self.cells.iter().enumerate() {
+            if self
+                .shown
+                .as_ref()
+                .is_some_and(|shown| shown[index] == cell)
+            {
+                continue;
+            }
+            let x = index as i32 % self.width;
+            let y = index as i32 / self.width;
+            if cursor != Some((x, y)) {
+                queue!(self.out, cursor::MoveTo(x as u16, y as u16))?;
+            }
+            if colors != Some((cell.foreground, cell.background)) {
+                queue!(
+                    self.out,
+                    SetForegroundColor(terminal_color(cell.foreground, self.truecolor)),
+                    SetBackgroundColor(terminal_color(cell.background, self.truecolor))
+                )?;
+                colors = Some((cell.foreground, cell.background));
+            }
+            queue!(self.out, Print(cell.glyph))?;
+            cursor = Some((x + 1, y));
+        }
+        self.out.flush()?;
+        self.shown = Some(self.cells.clone());
+        Ok(())
+    }
+}
+
+impl Drop for TerminalFrontend {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Undoes everything `TerminalFrontend::new` did to the terminal. Safe to
+/// call more than once.
+fn restore_terminal() {
+    let mut out = io::stdout();
+    let _ = execute!(
+        out,
+        style::ResetColor,
+        event::DisableMouseCapture,
+        cursor::Show,
+        terminal::LeaveAlternateScreen
+    );
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Terminals that don't say they take 24-bit colour get the nearest of the
+/// 256 standard ones, which nearly all of them have.
+fn terminal_color(color: Color, truecolor: bool) -> style::Color {
+    if truecolor {
+        style::Color::Rgb {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+        }
+    } else {
+        style::Color::AnsiValue(ansi_256(color))
+    }
+}
+
+/// The closest colour in the 6x6x6 cube or the grey ramp of the 256-colour
+/// palette.
+fn ansi_256(color: Color) -> u8 {
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let cube_index = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&index| (CUBE_LEVELS[index] as i32 - channel as i32).abs())
+            .unwrap()
+    };
+    let (r, g, b) = (
+        cube_index(color.r),
+        cube_index(color.g),
+        cube_index(color.b),
+    );
+    let cube = Color::new(CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+
+    // The greys run from 8 to 238 in steps of 10.
+    let average = (color.r as i32 + color.g as i32 + color.b as i32) / 3;
+    let grey_index = ((average - 3) / 10).clamp(0, 23);
+    let grey_level = (8 + grey_index * 10) as u8;
+    let grey = Color::new(grey_level, grey_level, grey_level);
+
+    if distance(color, grey) < distance(color, cube) {
+        232 + grey_index as u8
+    } else {
+        (16 + 36 * r + 6 * g + b) as u8
+    }
+}
+
+fn distance(a: Color, b: Color) -> i32 {
+    let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    channel(a.r, b.r) + channel(a.g, b.g) + channel(a.b, b.b)
+}
+
+fn key_from_crossterm(key: KeyEvent) -> Option<Key> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    match key.code {
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        KeyCode::Esc => Some(Key::Escape),
+        KeyCode::Enter => Some(Key::Enter {
+            alt: key.modifiers.contains(KeyModifiers::ALT),
+        }),
+        // Raw mode stops Ctrl+C from interrupting the game, so it quits
+        // the way Escape does instead.
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Escape),
+        KeyCode::Char(letter) => Some(Key::Char(letter)),
+        _ => None,
+    }
+}
+
+impl Frontend for TerminalFrontend {
+    fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn clear(&mut self) {
+        for cell in &mut self.cells {
+            *cell = BLANK;
+        }
+    }
+
+    fn put_char(
+        &mut self,
+        x: i32,
+        y: i32,
+        glyph: char,
+        foreground: Color,
+        background: Option<Color>,
+    ) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let cell = &mut self.cells[(y * self.width + x) as usize];
+        cell.glyph = glyph;
+        cell.foreground = foreground;
+        if let Some(background) = background {
+            cell.background = background;
+        }
+    }
+
+    fn present(&mut self) {
+        // The terminal went away, most likely with the SSH connection.
+        if self.draw().is_err() {
+            self.closed = true;
+        }
+    }
+
+    /// Waits up to a frame for input, which keeps the game loop from
+    /// redrawing any faster than that.
+    fn poll_input(&mut self) -> Option<Input> {
+        match event::poll(self.frame_time) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(_) => {
+                self.closed = true;
+                return None;
+            }
+        }
+        match event::read() {
+            Ok(Event::Key(key)) => key_from_crossterm(key).map(Input::Key),
+            Ok(Event::Mouse(mouse)) if mouse.kind == MouseEventKind::Moved => {
+                Some(Input::Mouse(mouse.column as i32, mouse.row as i32))
+            }
+            Ok(Event::Resize(width, height)) => {
+                self.resize(width, height);
+                None
+            }
+            Ok(_) => None,
+            Err(_) => {
+                self.closed = true;
+                None
+            }
+        }
+    }
+
+    fn wait_for_key(&mut self) -> Key {
+        loop {
+            match event::read() {
+                Ok(Event::Key(key)) => {
+                    if let Some(key) = key_from_crossterm(key) {
+                        return key;
+                    }
+                }
+                Ok(Event::Resize(width, height)) => self.resize(width, height),
+                Ok(_) => {}
+                Err(_) => {
+                    self.closed = true;
+                    return Key::Escape;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{LIGHT_GREY, RED};
+
+    #[test]
+    fn colours_map_onto_the_256_colour_palette() {
+        assert_eq!(ansi_256(BLACK), 16);
+        assert_eq!(ansi_256(WHITE), 231);
+        assert_eq!(ansi_256(RED), 196);
+        assert_eq!(ansi_256(Color::new(128, 128, 128)), 244);
+        assert_eq!(ansi_256(LIGHT_GREY), 247);
+    }
+
+    #[test]
+    fn ctrl_c_quits_like_escape() {
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(key_from_crossterm(ctrl_c), Some(Key::Escape));
+        let c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE);
+        assert_eq!(key_from_crossterm(c), Some(Key::Char('c')));
+    }
+}
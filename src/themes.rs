@@ -0,0 +1,211 @@
+use rand::{Rng, StdRng};
+use serde::Deserialize;
+
+use crate::color::*;
+use crate::entity::Entity;
+use crate::map::Map;
+use crate::rect::Rect;
+use crate::spawn::Transition;
+use crate::tile::TileKind;
+
+/// How much likelier a monster or item is to turn up in a room whose theme it
+/// lists.
+const THEME_WEIGHT_BONUS: u32 = 5;
+
+const FLOODED_WATER_CHANCE: f32 = 0.6;
+const BARRACKS_EXTRA_MONSTERS: u32 = 2;
+const ARMORY_MIN_RACKS: i32 = 2;
+const ARMORY_MAX_RACKS: i32 = 4;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum RoomTheme {
+    Library,
+    Armory,
+    Barracks,
+    Shrine,
+    Flooded,
+}
+
+pub const THEMES: &[RoomTheme] = &[
+    RoomTheme::Library,
+    RoomTheme::Armory,
+    RoomTheme::Barracks,
+    RoomTheme::Shrine,
+    RoomTheme::Flooded,
+];
+
+impl RoomTheme {
+    /// Added to how many monsters the room can hold.
+    pub fn extra_monsters(self) -> u32 {
+        match self {
+            RoomTheme::Barracks => BARRACKS_EXTRA_MONSTERS,
+            _ => 0,
+        }
+    }
+}
+
+/// Furniture standing about in a themed room. Features never block and
+/// cannot be picked up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    Lectern,
+    WeaponRack,
+    ArmourStand,
+    Bedroll,
+    Altar,
+    Candle,
+}
+
+impl Feature {
+    pub fn make(self, x: i32, y: i32) -> Entity {
+        use Feature::*;
+        let (char, name, color) = match self {
+            Lectern => ('&', "lectern", Color::new(140, 90, 40)),
+            WeaponRack => ('/', "weapon rack", LIGHT_GREY),
+            ArmourStand => ('[', "armour stand", LIGHT_GREY),
+            Bedroll => ('=', "bedroll", Color::new(150, 110, 70)),
+            Altar => ('_', "altar", WHITE),
+            Candle => (',', "candle", LIGHT_YELLOW),
+        };
+        Entity::new(x, y, char, name, color, false)
+    }
+}
+
+/// Scales up `weights` when `theme` is one of the `favoured` themes.
+pub fn favour(
+    weights: Vec<Transition>,
+    favoured: &[RoomTheme],
+    theme: Option<RoomTheme>,
+) -> Vec<Transition> {
+    if !theme.is_some_and(|theme| favoured.contains(&theme)) {
+        return weights;
+    }
+    weights
+        .into_iter()
+        .map(|transition| Transition {
+            value: transition.value * THEME_WEIGHT_BONUS,
+            ..transition
+        })
+        .collect()
+}
+
+/// Furnishes `room` for its theme: changes its tiles and returns the
+/// features to put in it. Furniture along the walls keeps clear of every
+/// way in, so the room stays as connected as it was.
+pub fn decorate(
+    room: Rect,
+    theme: RoomTheme,
+    map: &mut Map,
+    rng: &mut StdRng,
+) -> Vec<((i32, i32), Feature)> {
+    let along_walls: Vec<_> = room
+        .interior()
+        .into_iter()
+        .filter(|&(x, y)| {
+            let on_edge =
+                x == room.x1 + 1 || x == room.x2 - 1 || y == room.y1 + 1 || y == room.y2 - 1;
+            on_edge && !near_entrance(room, x, y, map)
+        })
+        .collect();
+
+    let mut features = vec![];
+    match theme {
+        RoomTheme::Library => {
+            for &(x, y) in &along_walls {
+                map.set_kind(x, y, TileKind::Bookshelf);
+            }
+            features.push((room.center(), Feature::Lectern));
+        }
+        RoomTheme::Armory => {
+            let mut spots = along_walls;
+            for _ in 0..rng.gen_range(ARMORY_MIN_RACKS, ARMORY_MAX_RACKS + 1) {
+                if spots.is_empty() {
+                    break;
+                }
+                let spot = spots.swap_remove(rng.gen_range(0, spots.len()));
+                let feature = if rng.gen() {
+                    Feature::WeaponRack
+                } else {
+                    Feature::ArmourStand
+                };
+                features.push((spot, feature));
+            }
+        }
+        RoomTheme::Barracks => {
+            for &(x, y) in along_walls.iter().filter(|&&(x, y)| (x + y) % 2 == 0) {
+                features.push(((x, y), Feature::Bedroll));
+            }
+        }
+        RoomTheme::Shrine => {
+            let (center_x, center_y) = room.center();
+            features.push(((center_x, center_y), Feature::Altar));
+            for &(dx, dy) in &[(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                features.push(((center_x + dx, center_y + dy), Feature::Candle));
+            }
+        }
+        RoomTheme::Flooded => {
+            for (x, y) in room.interior() {
+                if rng.gen::<f32>() < FLOODED_WATER_CHANCE {
+                    map.set_kind(x, y, TileKind::Water);
+                }
+            }
+        }
+    }
+    features
+}
+
+/// Whether an inside tile touches a gap in the room's walls.
+fn near_entrance(room: Rect, x: i32, y: i32, map: &Map) -> bool {
+    (-1..=1).any(|dx| {
+        (-1..=1).any(|dy| {
+            let (wall_x, wall_y) = (x + dx, y + dy);
+            let on_perimeter =
+                wall_x == room.x1 || wall_x == room.x2 || wall_y == room.y1 || wall_y == room.y2;
+            on_perimeter && map[(wall_x, wall_y)].kind() != TileKind::Wall
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Tile;
+    use crate::mapgen;
+    use rand::SeedableRng;
+
+    #[test]
+    fn library_shelves_leave_the_door_clear() {
+        let mut map = Map::new(10, 10, Tile::wall());
+        let room = Rect::new(1, 1, 6, 6);
+        mapgen::create_room(room, &mut map);
+        map.set_kind(4, 1, TileKind::ClosedDoor);
+
+        let mut rng = StdRng::from_seed(&[0][..]);
+        decorate(room, RoomTheme::Library, &mut map, &mut rng);
+
+        assert_eq!(map[(2, 2)].kind(), TileKind::Bookshelf);
+        assert_eq!(map[(2, 6)].kind(), TileKind::Bookshelf);
+        for x in 3..=5 {
+            assert_eq!(map[(x, 2)].kind(), TileKind::Floor);
+        }
+        assert_eq!(map[(4, 4)].kind(), TileKind::Floor);
+    }
+
+    #[test]
+    fn favour_only_boosts_listed_themes() {
+        let weights = vec![Transition {
+            level: 1,
+            value: 10,
+        }];
+        let favoured = [RoomTheme::Barracks];
+        assert_eq!(
+            favour(weights.clone(), &favoured, Some(RoomTheme::Barracks))[0].value,
+            50
+        );
+        assert_eq!(
+            favour(weights.clone(), &favoured, Some(RoomTheme::Library))[0].value,
+            10
+        );
+        assert_eq!(favour(weights, &favoured, None)[0].value, 10);
+    }
+}
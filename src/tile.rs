@@ -1,24 +1,148 @@
-#[derive(Clone, Copy, Debug)]
-pub struct Tile {
-    pub blocked: bool,
-    pub explored: bool,
-    pub block_sight: bool,
+use crate::color::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileKind {
+    Floor,
+    Wall,
+    ClosedDoor,
+    OpenDoor,
+    Water,
+    Lava,
+    Grass,
+    Rubble,
+    SecretDoor,
+    SecretCorridor,
+    Bookshelf,
+    WallTorch,
+}
+
+/// How a kind of tile looks and behaves. `light` and `dark` are the
+/// background colours in and out of view.
+pub struct Terrain {
+    pub glyph: char,
+    pub glyph_color: Color,
+    pub light: Color,
+    pub dark: Color,
+    pub passable: bool,
+    pub blocks_sight: bool,
+    pub movement_cost: i32,
 }
 
-impl Tile {
-    pub fn empty() -> Self {
-        Tile {
-            blocked: false,
-            explored: false,
-            block_sight: false,
+impl TileKind {
+    pub fn terrain(self) -> Terrain {
+        use TileKind::*;
+        match self {
+            Floor => Terrain {
+                glyph: ' ',
+                glyph_color: BLACK,
+                light: Color::new(200, 180, 50),
+                dark: Color::new(50, 50, 150),
+                passable: true,
+                blocks_sight: false,
+                movement_cost: 1,
+            },
+            // Secrets pass for plain wall until someone finds them.
+            Wall | SecretDoor | SecretCorridor => Terrain {
+                glyph: ' ',
+                glyph_color: BLACK,
+                light: Color::new(130, 110, 50),
+                dark: Color::new(0, 0, 100),
+                passable: false,
+                blocks_sight: true,
+                movement_cost: 1,
+            },
+            ClosedDoor => Terrain {
+                glyph: '+',
+                glyph_color: Color::new(90, 50, 10),
+                light: Color::new(130, 110, 50),
+                dark: Color::new(0, 0, 100),
+                passable: false,
+                blocks_sight: true,
+                movement_cost: 1,
+            },
+            OpenDoor => Terrain {
+                glyph: '\'',
+                glyph_color: Color::new(90, 50, 10),
+                light: Color::new(200, 180, 50),
+                dark: Color::new(50, 50, 150),
+                passable: true,
+                blocks_sight: false,
+                movement_cost: 1,
+            },
+            Water => Terrain {
+                glyph: '~',
+                glyph_color: Color::new(170, 210, 255),
+                light: Color::new(40, 90, 200),
+                dark: Color::new(20, 30, 110),
+                passable: true,
+                blocks_sight: false,
+                movement_cost: 2,
+            },
+            Lava => Terrain {
+                glyph: '~',
+                glyph_color: YELLOW,
+                light: Color::new(220, 60, 0),
+                dark: Color::new(90, 20, 40),
+                passable: false,
+                blocks_sight: false,
+                movement_cost: 1,
+            },
+            Grass => Terrain {
+                glyph: '"',
+                glyph_color: Color::new(40, 120, 20),
+                light: Color::new(120, 170, 50),
+                dark: Color::new(40, 60, 120),
+                passable: true,
+                blocks_sight: false,
+                movement_cost: 1,
+            },
+            Rubble => Terrain {
+                glyph: ':',
+                glyph_color: Color::new(90, 80, 60),
+                light: Color::new(170, 150, 70),
+                dark: Color::new(45, 45, 130),
+                passable: true,
+                blocks_sight: false,
+                movement_cost: 2,
+            },
+            WallTorch => Terrain {
+                glyph: '*',
+                glyph_color: Color::new(255, 170, 60),
+                light: Color::new(130, 110, 50),
+                dark: Color::new(0, 0, 100),
+                passable: false,
+                blocks_sight: true,
+                movement_cost: 1,
+            },
+            Bookshelf => Terrain {
+                glyph: '#',
+                glyph_color: Color::new(140, 90, 40),
+                light: Color::new(130, 110, 50),
+                dark: Color::new(0, 0, 100),
+                passable: false,
+                blocks_sight: true,
+                movement_cost: 1,
+            },
         }
     }
 
-    pub fn wall() -> Self {
-        Tile {
-            blocked: true,
-            explored: false,
-            block_sight: true,
+    /// What a secret turns out to be once found, or `None` for anything
+    /// that is not a secret.
+    pub fn revealed(self) -> Option<TileKind> {
+        match self {
+            TileKind::SecretDoor => Some(TileKind::ClosedDoor),
+            TileKind::SecretCorridor => Some(TileKind::Floor),
+            _ => None,
         }
     }
 }
+
+/// What the player remembers of a tile. Unlike the rest of the tile it can
+/// be changed freely, through `Map::memory_mut`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TileMemory {
+    pub explored: bool,
+    /// The glyph and colour of what the player last saw standing or lying
+    /// here, drawn dimmed while the tile is out of view.
+    pub remembered: Option<(char, Color)>,
+}
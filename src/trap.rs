@@ -0,0 +1,111 @@
+use rand::{Rng, StdRng};
+
+use crate::color::*;
+use crate::entity::Entity;
+use crate::map::Map;
+use crate::spawn::{SpawnTable, Transition};
+use crate::tile::TileKind;
+
+/// How many traps a level gets, by depth.
+pub const MAX_LEVEL_TRAPS: &[Transition] = &[
+    Transition { level: 1, value: 2 },
+    Transition { level: 3, value: 4 },
+    Transition { level: 5, value: 6 },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrapKind {
+    Dart,
+    Teleport,
+    Alarm,
+    ConfusionGas,
+}
+
+impl TrapKind {
+    pub fn name(self) -> &'static str {
+        use TrapKind::*;
+        match self {
+            Dart => "dart trap",
+            Teleport => "teleport trap",
+            Alarm => "alarm trap",
+            ConfusionGas => "confusion gas trap",
+        }
+    }
+
+    fn color(self) -> Color {
+        use TrapKind::*;
+        match self {
+            Dart => LIGHT_GREY,
+            Teleport => LIGHT_MAGENTA,
+            Alarm => LIGHT_ORANGE,
+            ConfusionGas => LIGHT_GREEN,
+        }
+    }
+}
+
+/// A trap lying on the floor. Hidden traps are not drawn until the player
+/// finds them, but they go off all the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub hidden: bool,
+}
+
+#[rustfmt::skip]
+pub fn trap_table() -> SpawnTable<TrapKind> {
+    SpawnTable::new()
+        .add(TrapKind::Dart, &[Transition { level: 1, value: 30 }])
+        .add(TrapKind::Alarm, &[Transition { level: 1, value: 15 }])
+        .add(TrapKind::ConfusionGas, &[Transition { level: 2, value: 20 }])
+        .add(TrapKind::Teleport, &[Transition { level: 3, value: 15 }])
+}
+
+pub fn make_trap(x: i32, y: i32, kind: TrapKind) -> Entity {
+    let mut entity = Entity::new(x, y, '^', kind.name(), kind.color(), false);
+    entity.trap = Some(Trap { kind, hidden: true });
+    entity
+}
+
+/// Up to `count` different floor tiles to hide traps on, leaving `taken`
+/// alone.
+pub fn trap_spots(
+    map: &Map,
+    taken: &[(i32, i32)],
+    count: u32,
+    rng: &mut StdRng,
+) -> Vec<(i32, i32)> {
+    let mut candidates: Vec<_> = map
+        .iter()
+        .filter(|&(location, tile)| tile.kind() == TileKind::Floor && !taken.contains(&location))
+        .map(|(location, _)| location)
+        .collect();
+
+    let mut spots = vec![];
+    while spots.len() < count as usize && !candidates.is_empty() {
+        let index = rng.gen_range(0, candidates.len());
+        spots.push(candidates.swap_remove(index));
+    }
+    spots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Tile;
+    use rand::SeedableRng;
+
+    #[test]
+    fn trap_spots_are_distinct_free_floor() {
+        let mut map = Map::new(6, 3, Tile::wall());
+        for x in 1..5 {
+            map.set_kind(x, 1, TileKind::Floor);
+        }
+        map.set_kind(4, 1, TileKind::Water);
+        let taken = [(1, 1)];
+
+        let mut rng = StdRng::from_seed(&[0][..]);
+        let mut spots = trap_spots(&map, &taken, 5, &mut rng);
+        spots.sort();
+        assert_eq!(spots, vec![(2, 1), (3, 1)]);
+    }
+}